@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use bytes::Bytes;
+use serde::Deserialize;
+use tracing::info;
+use warp::{Filter, Rejection, Reply};
+use warp::http::StatusCode;
+use crate::cors;
+use crate::handler::MockState;
+
+/// Prefix of the endpoints used to inspect and reset the mock state
+pub const ADMIN_PREFIX: &str = "__admin";
+
+/// Administration endpoints:
+/// - `GET /__admin/sequences`: number of calls served by each endpoint with a `responses` list.
+/// - `POST /__admin/sequences/reset`: starts every sequence over, or only the one given by `?endpoint=/api/key`.
+/// - `GET /__admin/scenarios`: current state of every scenario.
+/// - `PUT /__admin/scenarios/{name}/state`: moves a scenario to the state given as `{"state": "..."}`.
+/// - `POST /__admin/scenarios/reset`: puts every scenario back in the `Started` state.
+///
+/// They follow the top-level `cors` section, so a browser page can drive them.
+pub fn routes(state: MockState) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let cors = state.cors.clone();
+    let state = warp::any().map(move || state.clone());
+    let admin = warp::path(ADMIN_PREFIX);
+
+    let sequences = admin
+        .and(warp::path("sequences"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(state.clone())
+        .map(|state: MockState| warp::reply::json(&state.sequences.calls()));
+
+    let reset_sequences = admin
+        .and(warp::path!("sequences" / "reset"))
+        .and(warp::post())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(state.clone())
+        .map(|query: HashMap<String, String>, state: MockState| {
+            let endpoint = query.get("endpoint").map(String::as_str);
+            info!("🔁 Resetting sequences: {}", endpoint.unwrap_or("all"));
+            state.sequences.reset(endpoint);
+            StatusCode::NO_CONTENT
+        });
+
+    let scenarios = admin
+        .and(warp::path("scenarios"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(state.clone())
+        .map(|state: MockState| warp::reply::json(&state.scenarios.states()));
+
+    let set_scenario_state = admin
+        .and(warp::path!("scenarios" / String / "state"))
+        .and(warp::put())
+        // Read as bytes, so a bad payload is answered here instead of falling through to the mock
+        .and(warp::body::bytes())
+        .and(state.clone())
+        .map(|scenario: String, body: Bytes, state: MockState| {
+            let update: StateUpdate = match serde_json::from_slice(&body) {
+                Ok(update) => update,
+                Err(error) => {
+                    let message = format!("Expected {{\"state\": \"...\"}}: {}\n", error);
+                    return warp::reply::with_status(message, StatusCode::BAD_REQUEST).into_response();
+                }
+            };
+            info!("🎬 Setting scenario {} to state {}", scenario, update.state);
+            state.scenarios.set_state(&scenario, &update.state);
+            StatusCode::NO_CONTENT.into_response()
+        });
+
+    let reset_scenarios = admin
+        .and(warp::path!("scenarios" / "reset"))
+        .and(warp::post())
+        .and(state)
+        .map(|state: MockState| {
+            info!("🔁 Resetting scenarios");
+            state.scenarios.reset();
+            StatusCode::NO_CONTENT
+        });
+
+    let routes = sequences
+        .or(reset_sequences)
+        .or(scenarios)
+        .or(set_scenario_state)
+        .or(reset_scenarios);
+    cors::serve(cors, admin, routes)
+}
+
+#[derive(Debug, Deserialize)]
+struct StateUpdate {
+    state: String,
+}
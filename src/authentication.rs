@@ -1,57 +1,339 @@
+use std::collections::HashMap;
+use anyhow::bail;
 use base64::{Engine as _, engine::{general_purpose}};
-use serde_yaml::Value;
-use warp::reject::Reject;
-
-#[derive(Debug)]
-pub struct Unauthorized;
-
-impl Reject for Unauthorized {}
-
-pub fn validate_auth(auth: &Value, auth_header: Option<String>) -> bool {
-    if let Some(header) = auth_header {
-        if let Some(basic) = auth.get("basic") {
-            if let (Some(user), Some(password)) = (basic.get("user"), basic.get("password")) {
-                if let Ok(decoded) = general_purpose::STANDARD
-                    .decode(header.replace("Basic ", "")) {
-                    let creds = String::from_utf8_lossy(&decoded);
-                    let expected = format!("{}:{}", user.as_str().unwrap(), password.as_str().unwrap());
-                    return creds == expected;
-                }
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::info;
+use warp::http::header::AUTHORIZATION;
+use jsonwebtoken::DecodingKey;
+use crate::config::Authentication;
+use crate::jwt::{unverified_claims, Jwks, JwtConfig, PublicKey};
+use crate::matcher::Pattern;
+use crate::request::MockRequest;
+
+/// Outcome of checking the credentials of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Authenticated,
+    /// Missing or invalid credentials
+    Unauthenticated,
+    /// Valid credentials lacking a required scope, role or claim
+    Forbidden,
+}
+
+/// `issuer_key` verifies tokens of the built-in OAuth issuer, when one is configured.
+pub fn validate_auth(auth: &Authentication, request: &MockRequest, issuer_key: Option<&DecodingKey>) -> AuthOutcome {
+    let authenticated = |valid: bool| if valid { AuthOutcome::Authenticated } else { AuthOutcome::Unauthenticated };
+    match auth {
+        Authentication::Basic(basic) => authenticated(request
+            .header(AUTHORIZATION.as_str())
+            .is_some_and(|header| basic.validate_header(header))),
+        Authentication::Bearer(bearer) => request
+            .header(AUTHORIZATION.as_str())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map_or(AuthOutcome::Unauthenticated, |token| bearer.validate_token(token, issuer_key)),
+        Authentication::ApiKey(api_key) => authenticated(api_key.validate_request(request)),
+        // The most successful alternative wins: a forbidden token is reported over missing credentials
+        Authentication::AnyOf(alternatives) => alternatives
+            .iter()
+            .map(|alternative| validate_auth(alternative, request, issuer_key))
+            .min_by_key(|outcome| match outcome {
+                AuthOutcome::Authenticated => 0,
+                AuthOutcome::Forbidden => 1,
+                AuthOutcome::Unauthenticated => 2,
+            })
+            .unwrap_or(AuthOutcome::Unauthenticated),
+    }
+}
+
+/// `WWW-Authenticate` challenges answering a failed check, one per scheme that has one.
+pub fn challenges(auth: &Authentication, request: &MockRequest, outcome: AuthOutcome) -> Vec<String> {
+    match auth {
+        Authentication::Basic(basic) if outcome == AuthOutcome::Unauthenticated => {
+            vec![format!("Basic realm=\"{}\"", realm(&basic.realm))]
+        }
+        Authentication::Bearer(bearer) if outcome == AuthOutcome::Forbidden => {
+            if !bearer.has_rules() {
+                return Vec::new();
             }
+            let scope = if bearer.scopes.is_empty() {
+                String::new()
+            } else {
+                format!(", scope=\"{}\"", bearer.scopes.join(" "))
+            };
+            vec![format!("Bearer realm=\"{}\", error=\"insufficient_scope\"{}", realm(&bearer.realm), scope)]
+        }
+        Authentication::Bearer(bearer) => {
+            // A rejected token is reported as such, a missing one only gets the challenge (RFC 6750)
+            let sent_token = request
+                .header(AUTHORIZATION.as_str())
+                .is_some_and(|header| header.starts_with("Bearer "));
+            let error = if sent_token { ", error=\"invalid_token\"" } else { "" };
+            vec![format!("Bearer realm=\"{}\"{}", realm(&bearer.realm), error)]
+        }
+        Authentication::Basic(_) | Authentication::ApiKey(_) => Vec::new(),
+        Authentication::AnyOf(alternatives) => alternatives
+            .iter()
+            .flat_map(|alternative| challenges(alternative, request, outcome))
+            .collect(),
+    }
+}
+
+const DEFAULT_REALM: &str = "mockserver";
+
+fn realm(realm: &Option<String>) -> &str {
+    realm.as_deref().unwrap_or(DEFAULT_REALM)
+}
+
+/// Username and password expected in a Basic `Authorization` header.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuth {
+    pub user: String,
+    pub password: String,
+    /// Realm of the `WWW-Authenticate` challenge, `mockserver` by default
+    pub realm: Option<String>,
+}
+
+impl BasicAuth {
+    fn validate_header(&self, header: &str) -> bool {
+        header
+            .strip_prefix("Basic ")
+            .and_then(|encoded| general_purpose::STANDARD.decode(encoded).ok())
+            .is_some_and(|decoded| decoded == format!("{}:{}", self.user, self.password).as_bytes())
+    }
+}
+
+/// A bearer token must equal the configured `token`, be a JWT signed with the configured key, or both.
+///
+/// `scopes`, `roles` and `claims_match` then authorize the token: one lacking them is forbidden.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(from = "BearerFields")]
+pub struct BearerAuth {
+    pub token: Option<String>,
+    /// Realm of the `WWW-Authenticate` challenge, `mockserver` by default
+    pub realm: Option<String>,
+    /// Claims the token must carry, with the same values
+    pub claims: Map<String, Value>,
+    /// Scopes that must all be granted by the space-delimited `scope` claim
+    pub scopes: Vec<String>,
+    /// Roles of which the token must have at least one in its `roles_claim`
+    pub roles: Vec<String>,
+    /// Array claim listing the roles, `roles` by default; dots reach into objects, as in `realm_access.roles`
+    pub roles_claim: Option<String>,
+    /// Regular expressions that claims must match
+    pub claims_match: HashMap<String, Pattern>,
+    pub jwt: JwtConfig,
+}
+
+/// The keys of a `bearer` block as written in the configuration file, where the JWT settings sit
+/// next to the others; unknown keys are rejected, as a mistyped rule would be silently ignored.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BearerFields {
+    token: Option<String>,
+    realm: Option<String>,
+    #[serde(default)]
+    claims: Map<String, Value>,
+    #[serde(default)]
+    scopes: Vec<String>,
+    #[serde(default)]
+    roles: Vec<String>,
+    roles_claim: Option<String>,
+    #[serde(default)]
+    claims_match: HashMap<String, Pattern>,
+    secret: Option<String>,
+    public_key: Option<PublicKey>,
+    jwks: Option<Jwks>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    #[serde(default)]
+    leeway: u64,
+    #[serde(default)]
+    oauth: bool,
+}
+
+impl From<BearerFields> for BearerAuth {
+    fn from(fields: BearerFields) -> Self {
+        BearerAuth {
+            token: fields.token,
+            realm: fields.realm,
+            claims: fields.claims,
+            scopes: fields.scopes,
+            roles: fields.roles,
+            roles_claim: fields.roles_claim,
+            claims_match: fields.claims_match,
+            jwt: JwtConfig {
+                secret: fields.secret,
+                public_key: fields.public_key,
+                jwks: fields.jwks,
+                issuer: fields.issuer,
+                audience: fields.audience,
+                leeway: fields.leeway,
+                oauth: fields.oauth,
+            },
+        }
+    }
+}
+
+impl BearerAuth {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let key_sources = [self.jwt.secret.is_some(), self.jwt.public_key.is_some(), self.jwt.jwks.is_some(), self.jwt.oauth];
+        match key_sources.into_iter().filter(|given| *given).count() {
+            0 if self.token.is_none() => bail!("'bearer' needs a 'token' or a key: 'secret', 'public_key', 'jwks' or 'oauth'"),
+            0 | 1 => Ok(()),
+            _ => bail!("Only one of 'secret', 'public_key', 'jwks' or 'oauth' can be given"),
+        }
+    }
+
+    fn has_rules(&self) -> bool {
+        !self.scopes.is_empty() || !self.roles.is_empty() || !self.claims_match.is_empty()
+    }
+
+    fn validate_token(&self, provided_token: &str, issuer_key: Option<&DecodingKey>) -> AuthOutcome {
+        if let Some(token) = &self.token
+            && token != provided_token {
+            return AuthOutcome::Unauthenticated;
         }
 
-        if let Some(bearer) = auth.get("bearer") {
-            if let Some(token) = bearer.get("token") {
-                let provided_token = header.replace("Bearer ", "");
-                if provided_token == token.as_str().unwrap() {
-                    return validate_claims(bearer, &provided_token);
+        let claims = if self.jwt.verifies_signature() {
+            match self.jwt.verify(provided_token, issuer_key) {
+                Ok(claims) => claims,
+                Err(error) => {
+                    info!("❌ Rejected JWT: {:#}", error);
+                    return AuthOutcome::Unauthenticated;
                 }
             }
+        } else if self.token.is_some() {
+            unverified_claims(provided_token).unwrap_or_default()
+        } else {
+            return AuthOutcome::Unauthenticated;
+        };
+
+        if !validate_claims(&self.claims, &claims) {
+            // A verified token is genuine, so missing claims are a lack of permission; the claims of a
+            // static token are unverified, so a mismatch means it is not the expected token
+            if self.jwt.verifies_signature() {
+                info!("🚫 Token does not carry the required claims");
+                return AuthOutcome::Forbidden;
+            }
+            return AuthOutcome::Unauthenticated;
+        }
+        match self.authorize(&claims) {
+            Ok(()) => AuthOutcome::Authenticated,
+            Err(reason) => {
+                info!("🚫 Token is not authorized: {}", reason);
+                AuthOutcome::Forbidden
+            }
         }
     }
 
-    false
+    /// Checks the authorization rules, returning why the token fails them.
+    fn authorize(&self, claims: &Value) -> Result<(), String> {
+        let granted: Vec<&str> = match claims.get("scope") {
+            Some(Value::String(scope)) => scope.split_whitespace().collect(),
+            Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if let Some(scope) = self.scopes.iter().find(|scope| !granted.contains(&scope.as_str())) {
+            return Err(format!("missing scope '{}'", scope));
+        }
+
+        if !self.roles.is_empty() {
+            let roles_claim = self.roles_claim.as_deref().unwrap_or("roles");
+            let roles = roles_claim
+                .split('.')
+                .try_fold(claims, |value, key| value.get(key))
+                .and_then(Value::as_array);
+            if !roles.is_some_and(|roles| roles.iter().any(|role| role.as_str().is_some_and(|role| self.roles.iter().any(|r| r == role)))) {
+                return Err(format!("none of the roles {:?} in '{}'", self.roles, roles_claim));
+            }
+        }
+
+        for (claim, pattern) in &self.claims_match {
+            let matches = match claims.get(claim) {
+                Some(Value::String(value)) => pattern.is_match(value),
+                Some(Value::Array(values)) => values.iter().any(|value| claim_text(value).is_some_and(|text| pattern.is_match(&text))),
+                Some(value) => claim_text(value).is_some_and(|text| pattern.is_match(&text)),
+                None => false,
+            };
+            if !matches {
+                return Err(format!("claim '{}' does not match its pattern", claim));
+            }
+        }
+
+        Ok(())
+    }
 }
 
-fn validate_claims(expected_claims: &Value, token: &str) -> bool {
-    let decoded_claims: Value = serde_json::from_str(token).unwrap_or_default();
+/// Scalar claims as text, for matching them with a regular expression.
+fn claim_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
 
-    if let Some(expected_map) = expected_claims.as_mapping() {
-        for (key, expected_value) in expected_map.iter() {
-            if let Some(key_str) = key.as_str() {
-                if key_str == "token" {
-                    continue;
-                }
-                if let Some(actual_value) = decoded_claims.get(key_str) {
-                    if actual_value != expected_value {
-                        return false;
-                    }
-                } else {
-                    return false; // Expected claim is missing
-                }
+/// Every configured claim must be present in the token with the same value.
+fn validate_claims(expected_claims: &Map<String, Value>, claims: &Value) -> bool {
+    expected_claims
+        .iter()
+        .all(|(key, expected_value)| claims.get(key) == Some(expected_value))
+}
+
+/// Where an API key is read from and the keys accepted, each with the identity of its holder.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Header carrying the key, `X-Api-Key` when neither `query` nor `cookie` is given
+    pub header: Option<String>,
+    pub query: Option<String>,
+    pub cookie: Option<String>,
+    pub keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    pub key: String,
+    /// Who the key belongs to, for the logs
+    pub name: Option<String>,
+}
+
+const DEFAULT_API_KEY_HEADER: &str = "X-Api-Key";
+
+impl ApiKeyConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if [&self.header, &self.query, &self.cookie].into_iter().flatten().count() > 1 {
+            bail!("Only one of 'header', 'query' or 'cookie' can be given");
+        }
+        if self.keys.is_empty() {
+            bail!("'api_key' needs at least one of 'keys'");
+        }
+
+        Ok(())
+    }
+
+    fn provided_key<'a>(&self, request: &'a MockRequest) -> Option<&'a str> {
+        match (&self.header, &self.query, &self.cookie) {
+            (Some(header), _, _) => request.header(header),
+            (None, Some(query), _) => request.query.get(query).map(String::as_str),
+            (None, None, Some(cookie)) => request.cookie(cookie),
+            (None, None, None) => request.header(DEFAULT_API_KEY_HEADER),
+        }
+    }
+
+    fn validate_request(&self, request: &MockRequest) -> bool {
+        let Some(provided) = self.provided_key(request) else {
+            return false;
+        };
+        match self.keys.iter().find(|key| key.key == provided) {
+            Some(key) => {
+                info!("🔑 Authenticated API key of {}", key.name.as_deref().unwrap_or("unnamed client"));
+                true
             }
+            None => false,
         }
     }
-    
-    true
 }
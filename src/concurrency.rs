@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::timeout;
+use tracing::{info, warn};
+use warp::reject::Reject;
+use crate::config::Endpoint;
+
+/// Slots of the endpoints with `max_concurrent`, keyed by route.
+#[derive(Clone, Default)]
+pub struct ConcurrencyLimiter {
+    slots: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+#[derive(Debug)]
+pub struct Saturated;
+impl Reject for Saturated {}
+
+impl ConcurrencyLimiter {
+    /// Takes one of the endpoint's slots, held until the permit is dropped.
+    ///
+    /// When all are taken, waits up to `queue_timeout_ms` for one to free up, or fails at once without it.
+    pub async fn acquire(&self, path: &str, endpoint: &Endpoint) -> Result<Option<OwnedSemaphorePermit>, warp::Rejection> {
+        let Some(max_concurrent) = endpoint.max_concurrent else {
+            return Ok(None);
+        };
+        let slots = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_concurrent)))
+            .clone();
+
+        if let Ok(permit) = slots.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+        if let Some(queue_timeout_ms) = endpoint.queue_timeout_ms {
+            info!("🚦 {} requests in flight for {}, queueing", max_concurrent, path);
+            if let Ok(Ok(permit)) = timeout(Duration::from_millis(queue_timeout_ms), slots.acquire_owned()).await {
+                return Ok(Some(permit));
+            }
+        }
+
+        warn!("⚠️ Too many concurrent requests for {} | Limit: {}", path, max_concurrent);
+        Err(warp::reject::custom(Saturated))
+    }
+}
@@ -1,23 +1,408 @@
-use serde::Deserialize;
+use anyhow::{anyhow, bail, Context};
+use serde::{de, Deserialize, Deserializer};
 use std::{collections::HashMap, fs};
+use serde_json::Value as JsonValue;
 use serde_yaml::Value;
+use crate::authentication::{ApiKeyConfig, BasicAuth, BearerAuth};
+use crate::cors::CorsConfig;
+use crate::matcher::{BodyMatcher, RequestMatcher};
+use crate::oauth::{OAuthConfig, OAUTH_PATHS};
 use crate::rate_limit::RateLimit;
+use crate::request::MockRequest;
+use crate::resource::Resource;
+use crate::router::Router;
+use crate::scenario::{ScenarioStep, ScenarioTracker};
+use crate::sequence::{SequenceEntry, SequenceMode};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Endpoint {
+    /// Methods answered with the endpoint's own response; methods with a block of their own can be left out
+    #[serde(default)]
     pub method: Vec<String>,
-    pub file: String,
+    pub file: Option<String>,
+    /// Inline response body, instead of a file
+    pub body: Option<String>,
+    /// Inline JSON response body written as YAML, instead of a file
+    pub json: Option<JsonValue>,
     pub status_code: Option<u16>,
-    pub authentication: Option<Value>,
+    pub authentication: Option<Authentication>,
+    /// Response to a failed authentication, `401 Unauthorized` with `WWW-Authenticate` challenges by default
+    pub unauthorized: Option<ResponseDefinition>,
+    /// Response to a valid token lacking a required scope, role or claim, `403 Forbidden` by default
+    pub forbidden: Option<ResponseDefinition>,
     pub delay: Option<u64>,
+    /// Requests served at once; more wait for `queue_timeout_ms` or get a 503 straight away
+    pub max_concurrent: Option<usize>,
+    pub queue_timeout_ms: Option<u64>,
     pub rate_limit: Option<RateLimit>,
+    /// Name of a limit of the top-level `rate_limit_groups` shared with other endpoints
+    pub rate_limit_group: Option<String>,
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+    #[serde(default)]
+    pub template: bool,
+    /// Headers added to every response of the endpoint
+    #[serde(default)]
+    pub headers: HashMap<String, HeaderValues>,
+    /// Responses served one per call, in the order given by `sequence`
+    #[serde(default)]
+    pub responses: Vec<SequenceEntry>,
+    #[serde(default)]
+    pub sequence: SequenceMode,
+    /// REST collection served under the endpoint key and `{key}/{id}`
+    pub resource: Option<Resource>,
+    /// CORS settings overriding the top-level `cors` section for this endpoint
+    pub cors: Option<CorsConfig>,
+    /// Fixed responses per HTTP method, declared as `GET: { file, status_code, headers }`
+    #[serde(flatten, deserialize_with = "deserialize_methods")]
+    pub methods: HashMap<String, ResponseDefinition>,
+}
+
+/// Every key left over by the endpoint fields must be an HTTP method, checked before its block is
+/// deserialized so that a mistyped field is reported as such.
+fn deserialize_methods<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, ResponseDefinition>, D::Error> {
+    HashMap::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, block)| {
+            if !HTTP_METHODS.contains(&key.as_str()) {
+                return Err(de::Error::custom(format!("unknown field or HTTP method '{}'", key)));
+            }
+            serde_yaml::from_value(block)
+                .map(|definition| (key.clone(), definition))
+                .map_err(|error| de::Error::custom(format!("{}: {}", key, error)))
+        })
+        .collect()
+}
+
+/// A canned response: served as is, whatever the method.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ResponseDefinition {
+    pub file: Option<String>,
+    pub body: Option<String>,
+    pub json: Option<JsonValue>,
+    #[serde(alias = "status")]
+    pub status_code: Option<u16>,
+    #[serde(default)]
+    pub headers: HashMap<String, HeaderValues>,
+}
+
+/// A header value, or several for headers such as `Set-Cookie` that may be repeated.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum HeaderValues {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HeaderValues {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            HeaderValues::One(value) => std::slice::from_ref(value),
+            HeaderValues::Many(values) => values,
+        }
+    }
+}
+
+/// How an endpoint authenticates requests, written as `basic: {..}`, `bearer: {..}` or `api_key: {..}`,
+/// or as a list of these to accept any of several credentials.
+#[derive(Debug, Clone)]
+pub enum Authentication {
+    Basic(BasicAuth),
+    Bearer(Box<BearerAuth>),
+    ApiKey(ApiKeyConfig),
+    AnyOf(Vec<Authentication>),
+}
+
+impl<'de> Deserialize<'de> for Authentication {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Sequence(alternatives) => alternatives
+                .into_iter()
+                .map(serde_yaml::from_value)
+                .collect::<Result<_, _>>()
+                .map(Authentication::AnyOf)
+                .map_err(de::Error::custom),
+            Value::Mapping(schemes) if schemes.len() == 1 => {
+                let (scheme, settings) = schemes.into_iter().next().unwrap();
+                let settings_error = |error: serde_yaml::Error| de::Error::custom(format!("{}: {}", scheme.as_str().unwrap_or_default(), error));
+                match scheme.as_str() {
+                    Some("basic") => serde_yaml::from_value(settings).map(Authentication::Basic).map_err(settings_error),
+                    Some("bearer") => serde_yaml::from_value(settings).map(Authentication::Bearer).map_err(settings_error),
+                    Some("api_key") => serde_yaml::from_value(settings).map(Authentication::ApiKey).map_err(settings_error),
+                    _ => Err(de::Error::custom(format!(
+                        "unknown authentication scheme {:?}, expected 'basic', 'bearer' or 'api_key'",
+                        scheme.as_str().unwrap_or_default()
+                    ))),
+                }
+            }
+            _ => Err(de::Error::custom(
+                "expected one of 'basic', 'bearer' or 'api_key', or a list of them for alternative credentials",
+            )),
+        }
+    }
 }
 
-pub type Config = HashMap<String, Endpoint>;
+impl Authentication {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Authentication::Basic(_) => Ok(()),
+            Authentication::Bearer(bearer) => bearer.validate(),
+            Authentication::ApiKey(api_key) => api_key.validate(),
+            Authentication::AnyOf(alternatives) => {
+                if alternatives.is_empty() {
+                    bail!("At least one authentication scheme is required");
+                }
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    alternative.validate().with_context(|| format!("Invalid alternative #{}", index + 1))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether tokens of the built-in OAuth issuer are accepted.
+    pub fn uses_oauth(&self) -> bool {
+        match self {
+            Authentication::Bearer(bearer) => bearer.jwt.oauth,
+            Authentication::AnyOf(alternatives) => alternatives.iter().any(Authentication::uses_oauth),
+            _ => false,
+        }
+    }
+}
+
+/// An alternative response for an endpoint, used when its matcher accepts the request.
+///
+/// The conditions sit at the top of the variant and what it returns under `response`.
+/// Unknown keys are rejected, as a mistyped condition would match every request.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(from = "VariantFields")]
+pub struct Variant {
+    pub matcher: RequestMatcher,
+    pub step: ScenarioStep,
+    pub response: ResponseDefinition,
+}
+
+/// The keys of a variant as written in the configuration file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VariantFields {
+    #[serde(default)]
+    query: HashMap<String, String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    body: Option<BodyMatcher>,
+    scenario: Option<String>,
+    required_state: Option<String>,
+    new_state: Option<String>,
+    response: ResponseDefinition,
+}
+
+impl From<VariantFields> for Variant {
+    fn from(fields: VariantFields) -> Self {
+        Variant {
+            matcher: RequestMatcher { query: fields.query, headers: fields.headers, body: fields.body },
+            step: ScenarioStep { scenario: fields.scenario, required_state: fields.required_state, new_state: fields.new_state },
+            response: fields.response,
+        }
+    }
+}
+
+/// Where a response body comes from: exactly one of `file`, `body` or `json`.
+#[derive(Debug, Clone, Copy)]
+pub enum BodySource<'a> {
+    File(&'a str),
+    Inline(&'a str),
+    Json(&'a JsonValue),
+}
+
+fn body_source<'a>(
+    file: &'a Option<String>,
+    body: &'a Option<String>,
+    json: &'a Option<JsonValue>,
+) -> anyhow::Result<Option<BodySource<'a>>> {
+    match (file, body, json) {
+        (None, None, None) => Ok(None),
+        (Some(file), None, None) => Ok(Some(BodySource::File(file))),
+        (None, Some(body), None) => Ok(Some(BodySource::Inline(body))),
+        (None, None, Some(json)) => Ok(Some(BodySource::Json(json))),
+        _ => bail!("Only one of 'file', 'body' or 'json' can be given"),
+    }
+}
+
+impl ResponseDefinition {
+    pub fn source(&self) -> Option<BodySource<'_>> {
+        body_source(&self.file, &self.body, &self.json).ok().flatten()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if body_source(&self.file, &self.body, &self.json)?.is_none() {
+            bail!("One of 'file', 'body' or 'json' is required");
+        }
+
+        Ok(())
+    }
+}
+
+const HTTP_METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+impl Endpoint {
+    /// Returns the first variant matching the request whose scenario is in the required state, if any.
+    pub fn select_variant(&self, request: &MockRequest, scenarios: &ScenarioTracker) -> Option<&Variant> {
+        self.variants
+            .iter()
+            .find(|variant| scenarios.is_active(&variant.step) && variant.matcher.matches(request))
+    }
+
+    /// A method is allowed when listed in `method` or when it has its own response block.
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.method.iter().any(|m| m == method) || self.methods.contains_key(method)
+    }
+
+    /// Methods answered by the endpoint, as listed in an `Allow` header.
+    ///
+    /// `HEAD` is answered whenever `GET` is, and `OPTIONS` always is.
+    pub fn allowed_methods(&self) -> Vec<&'static str> {
+        HTTP_METHODS
+            .into_iter()
+            .filter(|method| match *method {
+                "HEAD" => self.allows_method("HEAD") || self.allows_method("GET"),
+                "OPTIONS" => true,
+                method => self.allows_method(method),
+            })
+            .collect()
+    }
+
+    pub fn source(&self) -> Option<BodySource<'_>> {
+        body_source(&self.file, &self.body, &self.json).ok().flatten()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let source = body_source(&self.file, &self.body, &self.json)?;
+        if self.method.is_empty() && self.methods.is_empty() {
+            bail!("No method is served: list them under 'method' or give them a block of their own");
+        }
+
+        for (method, definition) in &self.methods {
+            definition.validate().with_context(|| format!("Invalid {} response", method))?;
+        }
+
+        for (index, variant) in self.variants.iter().enumerate() {
+            variant.step.validate()
+                .and_then(|_| variant.response.validate())
+                .with_context(|| format!("Invalid variant #{}", index + 1))?;
+        }
+
+        for (index, entry) in self.responses.iter().enumerate() {
+            entry.response.validate().with_context(|| format!("Invalid response #{}", index + 1))?;
+        }
+
+        if let Some(resource) = &self.resource {
+            resource.validate().context("Invalid resource")?;
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.validate().context("Invalid rate limit")?;
+        }
+
+        match (self.max_concurrent, self.queue_timeout_ms) {
+            (Some(0), _) => bail!("'max_concurrent' must be positive"),
+            (None, Some(_)) => bail!("'queue_timeout_ms' is only used with 'max_concurrent'"),
+            _ => {}
+        }
+
+        if let Some(authentication) = &self.authentication {
+            authentication.validate().context("Invalid authentication")?;
+        }
+
+        for (name, failure) in [("unauthorized", &self.unauthorized), ("forbidden", &self.forbidden)] {
+            if let Some(failure) = failure {
+                if self.authentication.is_none() {
+                    bail!("'{}' is only used with 'authentication'", name);
+                }
+                body_source(&failure.file, &failure.body, &failure.json).with_context(|| format!("Invalid {} response", name))?;
+            }
+        }
+
+        if source.is_none()
+            && self.responses.is_empty()
+            && self.resource.is_none()
+            && let Some(method) = self.method.iter().find(|m| !self.methods.contains_key(*m)) {
+            bail!("One of 'file', 'body' or 'json' is required to serve {}", method);
+        }
+
+        Ok(())
+    }
+}
+
+/// The whole configuration file: server-wide settings and the endpoints, keyed by path.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Local OAuth2 / OpenID Connect issuer
+    pub oauth: Option<OAuthConfig>,
+    /// Server-wide limit, counting the requests to every endpoint
+    pub rate_limit: Option<RateLimit>,
+    /// Limits shared by the endpoints naming them in `rate_limit_group`
+    #[serde(default)]
+    pub rate_limit_groups: HashMap<String, RateLimit>,
+    #[serde(flatten)]
+    pub endpoints: HashMap<String, Endpoint>,
+}
+
+impl From<HashMap<String, Endpoint>> for Config {
+    fn from(endpoints: HashMap<String, Endpoint>) -> Self {
+        Config { endpoints, ..Default::default() }
+    }
+}
 
 pub fn load_config(config_file: &str) -> anyhow::Result<Config> {
     let config_data = fs::read_to_string(config_file)?;
-    let config: Config = serde_yaml::from_str(&config_data)?;
+    let config: Config = serde_yaml::from_str(&config_data)
+        .map_err(|error| locate_error(&config_data).unwrap_or_else(|| error.into()))?;
+    if let Some(rate_limit) = &config.rate_limit {
+        rate_limit.validate().context("Invalid server-wide rate limit")?;
+    }
+    for (name, rate_limit) in &config.rate_limit_groups {
+        rate_limit.validate().with_context(|| format!("Invalid rate limit group '{}'", name))?;
+    }
+    for (path, endpoint) in &config.endpoints {
+        endpoint.validate().with_context(|| format!("Invalid endpoint '{}'", path))?;
+        if let Some(group) = &endpoint.rate_limit_group
+            && !config.rate_limit_groups.contains_key(group) {
+            bail!("Endpoint '{}' uses the undefined rate limit group '{}'", path, group);
+        }
+    }
+    if config.oauth.is_some()
+        && let Some(path) = OAUTH_PATHS.iter().find(|path| config.endpoints.contains_key(**path)) {
+        bail!("Endpoint '{}' is served by the OAuth issuer", path);
+    }
+    if config.oauth.is_none()
+        && let Some((path, _)) = config.endpoints.iter().find(|(_, endpoint)| endpoint.authentication.as_ref().is_some_and(Authentication::uses_oauth)) {
+        bail!("Endpoint '{}' accepts OAuth tokens but no 'oauth' issuer is configured", path);
+    }
+    Router::new(&config.endpoints)?;
 
     Ok(config)
-}
\ No newline at end of file
+}
+
+/// Top-level keys that are settings rather than endpoint paths
+const SECTIONS: [&str; 4] = ["cors", "oauth", "rate_limit", "rate_limit_groups"];
+
+/// Endpoints are deserialized through `flatten`, which drops the location of errors:
+/// deserializes them one by one to name the endpoint at fault.
+fn locate_error(config_data: &str) -> Option<anyhow::Error> {
+    let endpoints: HashMap<String, Value> = serde_yaml::from_str(config_data).ok()?;
+    if let Some(key) = endpoints.keys().find(|key| !key.starts_with('/') && !SECTIONS.contains(&key.as_str())) {
+        return Some(anyhow!(
+            "Unknown top-level key '{}': expected an endpoint path starting with '/' or one of {}",
+            key,
+            SECTIONS.map(|section| format!("'{}'", section)).join(", ")
+        ));
+    }
+    endpoints
+        .into_iter()
+        .filter(|(path, _)| path.starts_with('/'))
+        .find_map(|(path, endpoint)| serde_yaml::from_value::<Endpoint>(endpoint).err().map(|error| (path, error)))
+        .map(|(path, error)| anyhow::Error::new(error).context(format!("Invalid endpoint '{}'", path)))
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use regex::Regex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use tracing::info;
+use warp::http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use warp::http::{HeaderMap, HeaderName, HeaderValue, Method, Response};
+use warp::{Filter, Rejection, Reply};
+
+const DEFAULT_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+const DEFAULT_HEADERS: [&str; 3] = ["Content-Type", "Authorization", "Accept"];
+
+/// CORS settings, given under the top-level `cors` key and optionally overridden per endpoint.
+///
+/// Settings left out of an endpoint's `cors` are taken from the top-level section, then from
+/// the defaults: any origin, the usual methods, `Content-Type`, `Authorization` and `Accept`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CorsConfig {
+    /// `false` turns CORS handling off, so no CORS headers are sent and preflights are plain `OPTIONS` requests
+    pub enabled: Option<bool>,
+    /// Allowed origins: `*`, exact origins or patterns such as `https://*.example.com`
+    pub origins: Option<Vec<OriginPattern>>,
+    pub methods: Option<Vec<String>>,
+    pub allowed_headers: Option<Vec<String>>,
+    pub exposed_headers: Option<Vec<String>>,
+    pub credentials: Option<bool>,
+    /// How long, in seconds, browsers may cache a preflight response
+    pub max_age: Option<u64>,
+}
+
+/// An allowed origin, where `*` matches any sequence of characters.
+#[derive(Debug, Clone)]
+pub struct OriginPattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl OriginPattern {
+    fn is_any(&self) -> bool {
+        self.pattern == "*"
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        self.regex.is_match(origin)
+    }
+}
+
+impl<'de> Deserialize<'de> for OriginPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        let escaped: Vec<String> = pattern.split('*').map(regex::escape).collect();
+        let regex = Regex::new(&format!("(?i)^{}$", escaped.join(".*"))).map_err(D::Error::custom)?;
+        Ok(OriginPattern { pattern, regex })
+    }
+}
+
+impl CorsConfig {
+    /// The settings of `endpoint`, falling back to these ones for anything it leaves out.
+    pub fn merge(&self, endpoint: Option<&CorsConfig>) -> CorsConfig {
+        let Some(endpoint) = endpoint else {
+            return self.clone();
+        };
+
+        CorsConfig {
+            enabled: endpoint.enabled.or(self.enabled),
+            origins: endpoint.origins.clone().or_else(|| self.origins.clone()),
+            methods: endpoint.methods.clone().or_else(|| self.methods.clone()),
+            allowed_headers: endpoint.allowed_headers.clone().or_else(|| self.allowed_headers.clone()),
+            exposed_headers: endpoint.exposed_headers.clone().or_else(|| self.exposed_headers.clone()),
+            credentials: endpoint.credentials.or(self.credentials),
+            max_age: endpoint.max_age.or(self.max_age),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn is_preflight(&self, method: &Method, headers: &HeaderMap) -> bool {
+        self.is_enabled()
+            && method == Method::OPTIONS
+            && header(headers, ORIGIN).is_some()
+            && header(headers, ACCESS_CONTROL_REQUEST_METHOD).is_some()
+    }
+
+    /// Answers a preflight request, or rejects it with `403` when the origin, method or headers are not allowed.
+    pub fn preflight(&self, headers: &HeaderMap) -> Response<Bytes> {
+        let origin = header(headers, ORIGIN).unwrap_or_default();
+        let method = header(headers, ACCESS_CONTROL_REQUEST_METHOD).unwrap_or_default();
+        let requested_headers: Vec<&str> = header(headers, ACCESS_CONTROL_REQUEST_HEADERS)
+            .map(|headers| headers.split(',').map(str::trim).filter(|h| !h.is_empty()).collect())
+            .unwrap_or_default();
+
+        let forbidden = if !self.allows_origin(origin) {
+            Some("origin not allowed")
+        } else if !self.methods().iter().any(|m| m.eq_ignore_ascii_case(method)) {
+            Some("request-method not allowed")
+        } else if !requested_headers.iter().all(|h| self.allows_header(h)) {
+            Some("header not allowed")
+        } else {
+            None
+        };
+        if let Some(reason) = forbidden {
+            info!("🚫 CORS preflight from {} forbidden: {}", origin, reason);
+            return Response::builder()
+                .status(403)
+                .body(format!("CORS request forbidden: {}\n", reason).into())
+                .unwrap();
+        }
+
+        let mut response = Response::builder()
+            .status(200)
+            .header(ACCESS_CONTROL_ALLOW_METHODS, self.methods().join(", "))
+            .body(Bytes::new())
+            .unwrap();
+        let headers = response.headers_mut();
+        if !requested_headers.is_empty() {
+            insert(headers, ACCESS_CONTROL_ALLOW_HEADERS, &requested_headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            insert(headers, ACCESS_CONTROL_MAX_AGE, &max_age.to_string());
+        }
+        self.apply(Some(origin), &mut response);
+        response
+    }
+
+    /// Adds the CORS headers for the request's origin, if it is allowed.
+    pub fn apply<B>(&self, origin: Option<&str>, response: &mut Response<B>) {
+        let Some(origin) = origin else {
+            return;
+        };
+        if !self.is_enabled() || !self.allows_origin(origin) {
+            return;
+        }
+
+        let credentials = self.credentials.unwrap_or(false);
+        let any_origin = self.origins.as_ref().is_none_or(|origins| origins.iter().any(OriginPattern::is_any));
+        let headers = response.headers_mut();
+        if any_origin && !credentials {
+            insert(headers, ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+        } else {
+            // Browsers do not accept `*` with credentials, so the origin is echoed back
+            insert(headers, ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.append(VARY, HeaderValue::from_static("Origin"));
+        }
+        if credentials {
+            insert(headers, ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+        }
+        if let Some(exposed) = self.exposed_headers.as_ref().filter(|exposed| !exposed.is_empty()) {
+            insert(headers, ACCESS_CONTROL_EXPOSE_HEADERS, &exposed.join(", "));
+        }
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.origins.as_ref().is_none_or(|origins| origins.iter().any(|pattern| pattern.matches(origin)))
+    }
+
+    fn allows_header(&self, header: &str) -> bool {
+        match &self.allowed_headers {
+            Some(allowed) => allowed.iter().any(|h| h == "*" || h.eq_ignore_ascii_case(header)),
+            None => DEFAULT_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(header)),
+        }
+    }
+
+    fn methods(&self) -> Vec<String> {
+        self.methods
+            .clone()
+            .unwrap_or_else(|| DEFAULT_METHODS.iter().map(|m| m.to_string()).collect())
+    }
+}
+
+/// Serves the built-in `routes` under this policy: answers their preflights and adds the CORS headers to their responses.
+///
+/// Preflights are `OPTIONS` requests, which `routes` do not accept, so `paths` tells which ones are theirs.
+pub fn serve<P, F, R>(
+    cors: Arc<CorsConfig>,
+    paths: P,
+    routes: F,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone
+where
+    P: Filter<Extract = (), Error = Rejection> + Clone + Send + Sync + 'static,
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let preflight_cors = cors.clone();
+    let preflight = paths
+        .and(warp::method())
+        .and(warp::header::headers_cloned())
+        .and_then(move |method: Method, headers: HeaderMap| {
+            let cors = preflight_cors.clone();
+            async move {
+                if cors.is_preflight(&method, &headers) {
+                    Ok(cors.preflight(&headers).into_response())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            }
+        });
+
+    let served = warp::header::headers_cloned()
+        .and(routes)
+        .map(move |headers: HeaderMap, reply: R| {
+            let mut response = reply.into_response();
+            cors.apply(header(&headers, ORIGIN), &mut response);
+            response
+        });
+
+    preflight.or(served).unify()
+}
+
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn insert(headers: &mut warp::http::HeaderMap, name: warp::http::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
@@ -1,153 +1,388 @@
-use crate::config::Endpoint;
-use std::collections::HashMap;
-use std::fs;
+use crate::config::{BodySource, Config, Endpoint, ResponseDefinition};
+use crate::cors::CorsConfig;
+use bytes::Bytes;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs as async_fs;
 use tokio::time::sleep;
 use tracing::info;
 use warp::{Filter, Rejection, Reply};
-use warp::http::header::AUTHORIZATION;
-use warp::http::Response;
-use warp::hyper::Body;
-use warp::reject::custom;
-use crate::authentication::{validate_auth, Unauthorized};
-use crate::rate_limit::{check_rate_limit, RateLimitTracker, RateLimited};
+use warp::http::header::{ALLOW, CONTENT_TYPE, ORIGIN, WWW_AUTHENTICATE};
+use warp::http::{HeaderValue, Method, Response, StatusCode};
+use crate::authentication::{challenges, validate_auth, AuthOutcome};
+use crate::concurrency::{ConcurrencyLimiter, Saturated};
+use crate::rate_limit::{check_rate_limit, RateLimitTracker, RateLimited, SharedRateLimits};
+use crate::patch::apply_patch;
+use crate::request::{self, MockRequest};
+use crate::resource::ResourceStore;
+use crate::response::{apply_headers, file_response, source_response};
+use crate::admin;
+use crate::oauth::{self, Issuer};
+use crate::router::{RouteMatch, Router};
+use crate::scenario::ScenarioTracker;
+use crate::sequence::SequenceTracker;
+use crate::template::Templates;
+
+/// Shared state available to every request
+#[derive(Clone)]
+pub struct MockState {
+    pub router: Arc<Router>,
+    pub responses_folder: String,
+    pub rate_limiter: RateLimitTracker,
+    pub rate_limits: Arc<SharedRateLimits>,
+    pub concurrency: ConcurrencyLimiter,
+    pub templates: Templates,
+    pub sequences: SequenceTracker,
+    pub scenarios: ScenarioTracker,
+    pub resources: ResourceStore,
+    pub cors: Arc<CorsConfig>,
+    pub issuer: Option<Issuer>,
+}
 
 pub fn routes(
-    endpoints: HashMap<String, Endpoint>,
+    config: impl Into<Config>,
     responses_folder: String,
     rate_limiter: RateLimitTracker,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    let endpoints = warp::any().map(move || endpoints.clone());
-    let responses_folder = warp::any().map(move || responses_folder.clone());
-    let rate_limiter = warp::any().map(move || rate_limiter.clone());
-
-    //TODO allow cors be passed via configuration file
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(vec!["GET", "POST", "PUT", "DELETE"])
-        .allow_headers(vec!["Content-Type", "Authorization", "Accept"])
-        .build();
-
-    warp::path::full()
-        .and(warp::method())
-        .and(warp::header::optional::<String>(AUTHORIZATION.as_str()))
-        .and(warp::body::bytes())
-        .and(endpoints)
-        .and(responses_folder)
-        .and(rate_limiter)
-        .and_then(process_request)
+    let config = config.into();
+    let state = MockState {
+        router: Arc::new(Router::new(&config.endpoints).expect("Invalid endpoint configuration")),
+        responses_folder,
+        rate_limiter,
+        rate_limits: Arc::new(SharedRateLimits {
+            server: config.rate_limit,
+            groups: config.rate_limit_groups,
+        }),
+        concurrency: ConcurrencyLimiter::default(),
+        templates: Templates::new(),
+        sequences: SequenceTracker::default(),
+        scenarios: ScenarioTracker::new(config.endpoints.values()),
+        resources: ResourceStore::default(),
+        cors: Arc::new(config.cors),
+        issuer: config.oauth.map(Issuer::new).transpose().expect("Invalid OAuth configuration"),
+    };
+    let admin = admin::routes(state.clone());
+    let oauth = oauth::routes(state.clone());
+    let state = warp::any().map(move || state.clone());
+
+    let mock = request::extract()
+        .and(state)
+        .and_then(process_request);
+
+    admin
+        .or(oauth)
+        .or(mock)
         .recover(handle_rejection)
-        .with(cors)
 }
 
-/// Handles the rate limit and processes the request
-async fn process_request(
-    path: warp::path::FullPath,
-    method: warp::http::Method,
-    auth_header: Option<String>,
-    body: bytes::Bytes,
-    endpoints: HashMap<String, Endpoint>,
-    responses_folder: String,
-    rate_limiter: RateLimitTracker,
-) -> Result<impl Reply, Rejection> {
-    let path_str = path.as_str().to_string();
+/// Resolves the route, answers CORS preflights, handles the rate limit and processes the request
+async fn process_request(request: MockRequest, state: MockState) -> Result<impl Reply, Rejection> {
+    info!("Received request: {} {}", request.method, request.path);
+    let origin = request.header(ORIGIN.as_str()).map(str::to_string);
+
+    let Some(route) = state.router.find(&request.path) else {
+        let mut response = Response::builder()
+            .status(404)
+            .body("Not Found\n".into())
+            .unwrap();
+        state.cors.apply(origin.as_deref(), &mut response);
+        return Ok(response);
+    };
 
-    if let Some(endpoint) = endpoints.get(&path_str) {
-        check_rate_limit(path_str.clone(), method.as_str(), endpoint.rate_limit.as_ref(), rate_limiter.clone()).await?;
+    info!("🧭 Matched route {} with params {:?}", route.pattern, route.params);
+    let cors = state.cors.merge(route.endpoint.cors.as_ref());
+    if cors.is_preflight(&request.method, &request.headers) {
+        return Ok(cors.preflight(&request.headers));
     }
 
-    handle_request(path, method, auth_header, body, endpoints, responses_folder).await
+    let result: Result<Response<Bytes>, Rejection> = async {
+        let limits = state.rate_limits.applicable(route.pattern, &request, route.endpoint);
+        let status = check_rate_limit(&limits, &request, state.rate_limiter.clone()).await?;
+        // The slot is held until the response is ready, delay included
+        let _slot = state.concurrency.acquire(route.pattern, route.endpoint).await?;
+        let mut response = handle_request(&route, request, &state).await?;
+        if let Some(status) = status {
+            status.apply(&mut response);
+        }
+        Ok(response)
+    }
+    .await;
+    // Rejections are turned into responses here so they carry the CORS headers too
+    let mut response = match result {
+        Ok(response) => response,
+        Err(rejection) => rejection_response(&rejection).ok_or(rejection)?,
+    };
+    cors.apply(origin.as_deref(), &mut response);
+
+    Ok(response)
 }
 
 pub async fn handle_request(
-    path: warp::path::FullPath,
-    method: warp::http::Method,
-    auth_header: Option<String>,
-    body: bytes::Bytes,
-    endpoints: HashMap<String, Endpoint>,
-    responses_folder: String,
-) -> Result<impl Reply, Rejection> {
-    info!("Received request: {} {}", method, path.as_str());
-
-    if let Some(endpoint) = endpoints.get(path.as_str()) {
-        if let Some(auth) = &endpoint.authentication {
-            if !validate_auth(auth, auth_header) {
-                info!("❌ Unauthorized access attempt to {}", path.as_str());
-                return Err(custom(Unauthorized));
-            }
+    route: &RouteMatch<'_>,
+    request: MockRequest,
+    state: &MockState,
+) -> Result<Response<Bytes>, Rejection> {
+    let is_head = request.method == Method::HEAD;
+    let mut response = respond(route, request, state).await?;
+
+    // HEAD responses keep the status and headers of the GET response, without its body
+    if is_head {
+        *response.body_mut() = Bytes::new();
+    }
+    Ok(response)
+}
+
+async fn respond(
+    route: &RouteMatch<'_>,
+    request: MockRequest,
+    state: &MockState,
+) -> Result<Response<Bytes>, Rejection> {
+    let endpoint = route.endpoint;
+    let path = route.pattern;
+    let method = &request.method;
+    // HEAD only looks at the response GET would get: it neither moves scenarios nor consumes responses,
+    // and its body is dropped, so templates are not rendered either and leave counters alone
+    let is_head = method == Method::HEAD;
+    let render = |contents: Vec<u8>| {
+        if endpoint.template && !is_head {
+            Ok(state.templates.render(&String::from_utf8(contents)?, route, &request)?.into_bytes())
+        } else {
+            Ok(contents)
         }
+    };
 
-        add_possible_delay(endpoint).await;
-        
-        let method_str = method.as_str();
-        let status_code = default_status_code(endpoint, method_str);
-
-        return if endpoint.method.iter().any(|m| m == method_str) {
-            match method_str {
-                "GET" => {
-                    let file_path = format!("{}/{}", responses_folder, endpoint.file);
-                    info!("📂 Fetching file from: {}", file_path);
-                    if let Ok(contents) = fs::read_to_string(&file_path) {
-                        Ok(Response::builder()
-                            .status(status_code)
-                            .header("Content-Type", "application/json")
-                            .body(contents)
-                            .unwrap())
-                    } else {
-                        info!("🚫 File not found: {}", file_path);
-                        Ok(Response::builder()
-                            .status(404)
-                            .body("Not Found\n".into())
-                            .unwrap())
-                    }
-                }
-                "POST" | "PUT" => {
-                    let file_path = format!("{}/{}", responses_folder, endpoint.file);
-                    info!("📂 Saving file to: {}", file_path);
-                    if (async_fs::write(file_path, body).await).is_err() {
-                        return Ok(Response::builder()
-                            .status(500)
-                            .body("Internal Server Error\n".into())
-                            .unwrap());
-                    }
-                    Ok(Response::builder()
-                        .status(status_code)
-                        .body("Created\n".into())
-                        .unwrap())
-                }
-                "DELETE" => {
-                    let file_path = format!("{}/{}", responses_folder, endpoint.file);
-                    info!("📂 Deleting file from: {}", file_path);
-                    if (async_fs::remove_file(file_path).await).is_err() {
-                        return Ok(Response::builder()
-                            .status(404)
-                            .body("File not Found\n".into())
-                            .unwrap());
-                    }
-                    Ok(Response::builder()
-                        .status(status_code)
-                        .body("".into())
-                        .unwrap())
-                }
-                _ => Ok(Response::builder()
-                    .status(405)
-                    .body("Method not allowed\n".into())
-                    .unwrap()),
+    if let Some(auth) = &endpoint.authentication {
+        let outcome = validate_auth(auth, &request, state.issuer.as_ref().map(Issuer::decoding_key));
+        let (definition, status_code) = match outcome {
+            AuthOutcome::Authenticated => (None, 0),
+            AuthOutcome::Unauthenticated => {
+                info!("❌ Unauthorized access attempt to {}", path);
+                (endpoint.unauthorized.as_ref(), 401)
+            }
+            AuthOutcome::Forbidden => {
+                info!("🚫 Forbidden access attempt to {}", path);
+                (endpoint.forbidden.as_ref(), 403)
             }
-        } else {
-            info!("🚫 Method not allowed: {} {}", method, path.as_str());
-            Ok(Response::builder()
-                .status(405)
-                .body("Method not allowed\n".into())
-                .unwrap())
         };
+        if outcome != AuthOutcome::Authenticated {
+            let challenges = challenges(auth, &request, outcome);
+            return Ok(auth_failure_response(route, definition, status_code, &challenges, &state.responses_folder, render));
+        }
+    }
+
+    add_possible_delay(endpoint).await;
+
+    let allowed = allowed_methods(route);
+    if !allowed.contains(&method.as_str()) {
+        info!("🚫 Method not allowed: {} {}", method, path);
+        return Ok(allow_response(405, &allowed, "Method not allowed\n"));
+    }
+
+    // HEAD is served like GET and OPTIONS lists the allowed methods, unless they have their own response block
+    let method_str = match method.as_str() {
+        "HEAD" if !endpoint.methods.contains_key("HEAD") => "GET",
+        "OPTIONS" if !endpoint.methods.contains_key("OPTIONS") => {
+            let mut response = allow_response(204, &allowed, "");
+            apply_headers(&mut response, &endpoint.headers, route);
+            return Ok(response);
+        }
+        method_str => method_str,
+    };
+
+    // Matching variants, per-method blocks and response lists are canned responses, whatever the method
+    if let Some(variant) = endpoint.select_variant(&request, &state.scenarios) {
+        info!("🔀 Selected variant {:?} for {}", variant.matcher, path);
+        if !is_head {
+            state.scenarios.advance(&variant.step);
+        }
+        return Ok(canned_response(route, &variant.response, method_str, &state.responses_folder, render));
+    }
+
+    if let Some(definition) = endpoint.methods.get(method_str) {
+        info!("📌 Using {} response block for {}", method_str, path);
+        return Ok(canned_response(route, definition, method_str, &state.responses_folder, render));
     }
 
-    Ok(Response::builder()
-        .status(404)
-        .body("Not Found\n".into())
-        .unwrap())
+    if let Some(resource) = &endpoint.resource {
+        let mut response = state.resources.handle(resource, route, &request, &state.responses_folder).await;
+        apply_headers(&mut response, &endpoint.headers, route);
+        return Ok(response);
+    }
+
+    let entry = if is_head {
+        state.sequences.peek(path, &endpoint.responses, endpoint.sequence)
+    } else {
+        state.sequences.next(path, &endpoint.responses, endpoint.sequence)
+    };
+    if let Some(entry) = entry {
+        info!("🔢 Using next {:?} response for {}", endpoint.sequence, path);
+        return Ok(canned_response(route, &entry.response, method_str, &state.responses_folder, render));
+    }
+
+    let status_code = default_status_code(endpoint.status_code, method_str);
+    let file_path = match endpoint.source() {
+        Some(BodySource::File(file)) => format!("{}/{}", state.responses_folder, route.expand(file)),
+        // Inline bodies have nothing to store or delete, so they are returned for every method
+        Some(source) => {
+            let mut response = source_response(source, route, &state.responses_folder, status_code, render);
+            apply_headers(&mut response, &endpoint.headers, route);
+            return Ok(response);
+        }
+        None => {
+            info!("🚫 No response configured for {} {}", method, path);
+            return Ok(Response::builder()
+                .status(500)
+                .body("No response configured\n".into())
+                .unwrap());
+        }
+    };
+
+    let mut response = match method_str {
+        "GET" => file_response(&file_path, status_code, render),
+        "POST" | "PUT" => {
+            info!("📂 Saving file to: {}", file_path);
+            if (async_fs::write(file_path, &request.body).await).is_err() {
+                return Ok(Response::builder()
+                    .status(500)
+                    .body("Internal Server Error\n".into())
+                    .unwrap());
+            }
+            Response::builder()
+                .status(status_code)
+                .body("Created\n".into())
+                .unwrap()
+        }
+        "PATCH" => patch_file(&file_path, &request, status_code).await,
+        "DELETE" => {
+            info!("📂 Deleting file from: {}", file_path);
+            if (async_fs::remove_file(file_path).await).is_err() {
+                return Ok(Response::builder()
+                    .status(404)
+                    .body("File not Found\n".into())
+                    .unwrap());
+            }
+            Response::builder()
+                .status(status_code)
+                .body("".into())
+                .unwrap()
+        }
+        _ => Response::builder()
+            .status(405)
+            .body("Method not allowed\n".into())
+            .unwrap(),
+    };
+
+    apply_headers(&mut response, &endpoint.headers, route);
+    Ok(response)
+}
+
+/// Applies a JSON Merge Patch or JSON Patch request to a stored JSON file and returns the result.
+async fn patch_file(file_path: &str, request: &MockRequest, status_code: u16) -> Response<Bytes> {
+    let Ok(contents) = async_fs::read(file_path).await else {
+        return Response::builder()
+            .status(404)
+            .body("File not Found\n".into())
+            .unwrap();
+    };
+    let Ok(mut document) = serde_json::from_slice::<serde_json::Value>(&contents) else {
+        return Response::builder()
+            .status(422)
+            .body("Stored response is not JSON and cannot be patched\n".into())
+            .unwrap();
+    };
+    if let Err(error) = apply_patch(&mut document, request.header(CONTENT_TYPE.as_str()), &request.body) {
+        info!("❌ Patch failed for {}: {}", file_path, error.message);
+        return error.response();
+    }
+
+    let patched = serde_json::to_vec(&document).unwrap_or_default();
+    info!("📂 Saving patched file to: {}", file_path);
+    if (async_fs::write(file_path, &patched).await).is_err() {
+        return Response::builder()
+            .status(500)
+            .body("Internal Server Error\n".into())
+            .unwrap();
+    }
+    Response::builder()
+        .status(status_code)
+        .header(CONTENT_TYPE, "application/json")
+        .body(patched.into())
+        .unwrap()
+}
+
+/// Methods answered for the matched route, as listed in an `Allow` header.
+fn allowed_methods(route: &RouteMatch<'_>) -> Vec<&'static str> {
+    let endpoint = route.endpoint;
+    let methods = endpoint.allowed_methods();
+    match &endpoint.resource {
+        Some(resource) => methods
+            .into_iter()
+            .filter(|method| endpoint.methods.contains_key(*method) || resource.serves(route, method))
+            .collect(),
+        None => methods,
+    }
+}
+
+fn allow_response(status_code: u16, allowed: &[&str], body: &'static str) -> Response<Bytes> {
+    Response::builder()
+        .status(status_code)
+        .header(ALLOW, allowed.join(", "))
+        .body(body.into())
+        .unwrap()
+}
+
+fn canned_response(
+    route: &RouteMatch<'_>,
+    definition: &ResponseDefinition,
+    method_str: &str,
+    responses_folder: &str,
+    render: impl Fn(Vec<u8>) -> anyhow::Result<Vec<u8>>,
+) -> Response<Bytes> {
+    let status_code = default_status_code(definition.status_code, method_str);
+    let mut response = match definition.source() {
+        Some(source) => source_response(source, route, responses_folder, status_code, render),
+        None => Response::builder()
+            .status(500)
+            .body("No response configured\n".into())
+            .unwrap(),
+    };
+    apply_headers(&mut response, &route.endpoint.headers, route);
+    apply_headers(&mut response, &definition.headers, route);
+    response
+}
+
+/// Answers a failed authentication or authorization with the endpoint's `unauthorized` or `forbidden` response.
+///
+/// Responses keeping the default status carry the `WWW-Authenticate` challenges, unless they set that header themselves.
+fn auth_failure_response(
+    route: &RouteMatch<'_>,
+    definition: Option<&ResponseDefinition>,
+    default_status_code: u16,
+    challenges: &[String],
+    responses_folder: &str,
+    render: impl Fn(Vec<u8>) -> anyhow::Result<Vec<u8>>,
+) -> Response<Bytes> {
+    let definition = definition.cloned().unwrap_or_default();
+    let status_code = definition.status_code.unwrap_or(default_status_code);
+    let mut response = match definition.source() {
+        Some(source) => source_response(source, route, responses_folder, status_code, render),
+        None => {
+            let reason = StatusCode::from_u16(status_code).ok().and_then(|status| status.canonical_reason());
+            Response::builder()
+                .status(status_code)
+                .body(format!("{}\n", reason.unwrap_or("Unauthorized")).into())
+                .unwrap()
+        }
+    };
+    if status_code == default_status_code {
+        for challenge in challenges {
+            if let Ok(value) = HeaderValue::from_str(challenge) {
+                response.headers_mut().append(WWW_AUTHENTICATE, value);
+            }
+        }
+    }
+    apply_headers(&mut response, &definition.headers, route);
+    response
 }
 
 async fn add_possible_delay(endpoint: &Endpoint) {
@@ -157,9 +392,9 @@ async fn add_possible_delay(endpoint: &Endpoint) {
     }
 }
 
-fn default_status_code(endpoint: &Endpoint, method_str: &str) -> u16 {
-    endpoint.status_code.unwrap_or(match method_str {
-        "GET" => 200,
+fn default_status_code(status_code: Option<u16>, method_str: &str) -> u16 {
+    status_code.unwrap_or(match method_str {
+        "GET" | "PATCH" | "HEAD" | "OPTIONS" => 200,
         "POST" | "PUT" => 201,
         "DELETE" => 204,
         _ => 405,
@@ -168,19 +403,23 @@ fn default_status_code(endpoint: &Endpoint, method_str: &str) -> u16 {
 
 /// Custom rejection handler for returning proper error responses
 async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
-    if err.find::<Unauthorized>().is_some() {
-        let response: Response<Body> = Response::builder()
-            .status(401)
-            .body(Body::from("Unauthorized\n"))
-            .unwrap();
-        return Ok(response);
-    } else if err.find::<RateLimited>().is_some() {
-        let response: Response<Body> = Response::builder()
+    rejection_response(&err).ok_or(err)
+}
+
+fn rejection_response(err: &Rejection) -> Option<Response<Bytes>> {
+    if let Some(RateLimited(status)) = err.find::<RateLimited>() {
+        let mut response = Response::builder()
             .status(429)
-            .body(Body::from("Rate limit exceeded\n"))
+            .body("Rate limit exceeded\n".into())
             .unwrap();
-        return Ok(response);
+        status.apply(&mut response);
+        Some(response)
+    } else if err.find::<Saturated>().is_some() {
+        Some(Response::builder()
+            .status(503)
+            .body("Service Unavailable\n".into())
+            .unwrap())
+    } else {
+        None
     }
-    
-    Err(err)
 }
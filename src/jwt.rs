@@ -0,0 +1,170 @@
+use std::fmt;
+use std::fs;
+use anyhow::{anyhow, bail, Context};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Header, Validation};
+use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+
+/// How JWT bearer tokens are verified, given next to `token` and `claims` in a `bearer` block.
+///
+/// One key source is used: `secret` for HS256, or `public_key` (a PEM file) or `jwks`
+/// (a JWKS file, the key being picked by the token's `kid`) for RS256 and ES256, or `oauth: true`
+/// for the ES256 tokens of the built-in issuer. Key files are read once, when the configuration is loaded.
+/// `exp` and `nbf` are enforced when present, `iss` and `aud` are required when configured.
+#[derive(Debug, Clone, Default)]
+pub struct JwtConfig {
+    pub secret: Option<String>,
+    pub public_key: Option<PublicKey>,
+    pub jwks: Option<Jwks>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Clock skew tolerated for `exp` and `nbf`, in seconds
+    pub leeway: u64,
+    /// Accepts tokens minted by the built-in OAuth issuer
+    pub oauth: bool,
+}
+
+impl JwtConfig {
+    /// Whether a key is configured, so tokens must be signed JWTs.
+    pub fn verifies_signature(&self) -> bool {
+        self.secret.is_some() || self.public_key.is_some() || self.jwks.is_some() || self.oauth
+    }
+
+    /// Checks the signature and registered claims of `token`, returning its claims.
+    ///
+    /// `issuer_key` is the key of the built-in OAuth issuer, when one is configured.
+    pub fn verify(&self, token: &str, issuer_key: Option<&DecodingKey>) -> anyhow::Result<Value> {
+        let header = decode_header(token).context("Malformed JWT")?;
+        let key = self.decoding_key(&header, issuer_key)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.required_spec_claims.clear();
+        validation.validate_nbf = true;
+        validation.leeway = self.leeway;
+        match &self.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                validation.required_spec_claims.insert("aud".to_string());
+            }
+            None => validation.validate_aud = false,
+        }
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+
+        Ok(decode::<Value>(token, &key, &validation)?.claims)
+    }
+
+    /// The key for the token's algorithm; an algorithm that does not fit the configured key is refused.
+    fn decoding_key(&self, header: &Header, issuer_key: Option<&DecodingKey>) -> anyhow::Result<DecodingKey> {
+        match (header.alg, &self.secret, &self.public_key, &self.jwks) {
+            (Algorithm::ES256, None, None, None) if self.oauth => {
+                issuer_key.cloned().ok_or_else(|| anyhow!("No 'oauth' issuer is configured"))
+            }
+            (Algorithm::HS256, Some(secret), _, _) => Ok(DecodingKey::from_secret(secret.as_bytes())),
+            (alg, _, Some(public_key), _) if alg == public_key.algorithm => Ok(public_key.key.clone()),
+            (Algorithm::RS256 | Algorithm::ES256, _, _, Some(jwks)) => jwks.find(header.kid.as_deref()).cloned(),
+            (alg, ..) => bail!("{:?} tokens are not accepted by this endpoint", alg),
+        }
+    }
+}
+
+/// A PEM public key (RSA for RS256 or EC for ES256), read and parsed when the configuration is loaded.
+#[derive(Clone)]
+pub struct PublicKey {
+    pub path: String,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+impl PublicKey {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let pem = read(path)?;
+        let (algorithm, key) = match DecodingKey::from_rsa_pem(&pem) {
+            Ok(key) => (Algorithm::RS256, key),
+            Err(_) => (
+                Algorithm::ES256,
+                DecodingKey::from_ec_pem(&pem).with_context(|| format!("{} is not an RSA or EC public key in PEM format", path))?,
+            ),
+        };
+
+        Ok(PublicKey { path: path.to_string(), algorithm, key })
+    }
+}
+
+/// The keys of a JWKS document, read and parsed when the configuration is loaded.
+#[derive(Clone)]
+pub struct Jwks {
+    pub path: String,
+    keys: Vec<(Option<String>, DecodingKey)>,
+}
+
+impl Jwks {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let jwks: JwkSet = serde_json::from_slice(&read(path)?)
+            .with_context(|| format!("{} is not a JWKS document", path))?;
+        let keys = jwks
+            .keys
+            .iter()
+            .map(|jwk| {
+                let kid = jwk.common.key_id.clone();
+                DecodingKey::from_jwk(jwk)
+                    .map(|key| (kid.clone(), key))
+                    .with_context(|| format!("Unsupported key '{}' in {}", kid.unwrap_or_default(), path))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if keys.is_empty() {
+            bail!("{} has no keys", path);
+        }
+
+        Ok(Jwks { path: path.to_string(), keys })
+    }
+
+    /// The key named by the token's `kid`, or the only key when the token has none.
+    fn find(&self, kid: Option<&str>) -> anyhow::Result<&DecodingKey> {
+        let key = match kid {
+            Some(kid) => self.keys.iter().find(|(key_id, _)| key_id.as_deref() == Some(kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => bail!("Token has no 'kid' to pick a key from {}", self.path),
+        };
+        key.map(|(_, key)| key).ok_or_else(|| anyhow!("No key '{}' in {}", kid.unwrap_or_default(), self.path))
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PublicKey::load(&String::deserialize(deserializer)?).map_err(|error| de::Error::custom(format!("{:#}", error)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Jwks {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Jwks::load(&String::deserialize(deserializer)?).map_err(|error| de::Error::custom(format!("{:#}", error)))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublicKey").field("path", &self.path).field("algorithm", &self.algorithm).finish()
+    }
+}
+
+impl fmt::Debug for Jwks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kids: Vec<_> = self.keys.iter().map(|(kid, _)| kid).collect();
+        f.debug_struct("Jwks").field("path", &self.path).field("kids", &kids).finish()
+    }
+}
+
+fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Cannot read {}", path))
+}
+
+/// Reads the claims of a JWT without checking its signature, used for static tokens.
+pub fn unverified_claims(token: &str) -> Option<Value> {
+    let payload = token.split('.').nth(1)?;
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+}
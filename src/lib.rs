@@ -1,5 +1,19 @@
+pub mod admin;
 pub mod authentication;
+pub mod concurrency;
 pub mod config;
+pub mod cors;
 pub mod handler;
+pub mod jwt;
+pub mod matcher;
+pub mod oauth;
+pub mod patch;
+pub mod request;
+pub mod resource;
+pub mod response;
+pub mod router;
+pub mod scenario;
+pub mod sequence;
 pub mod server;
+pub mod template;
 pub mod rate_limit;
\ No newline at end of file
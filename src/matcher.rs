@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use crate::request::MockRequest;
+
+/// Conditions an incoming request must satisfy for a variant to be selected.
+///
+/// Every listed query parameter and header must be present with exactly the given value;
+/// header names are compared case-insensitively.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RequestMatcher {
+    #[serde(default)]
+    pub query: HashMap<String, String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: Option<BodyMatcher>,
+}
+
+/// Conditions on the request body. Every condition that is set must hold.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct BodyMatcher {
+    /// The body parsed as JSON must be equal to this value.
+    pub equals_json: Option<Value>,
+    /// The body parsed as JSON must contain these fields and array elements, extra ones are ignored.
+    pub contains_json: Option<Value>,
+    /// The JSONPath expression must select at least one node of the body parsed as JSON.
+    pub json_path: Option<JsonPath>,
+    /// The raw body must match this regular expression.
+    pub matches: Option<Pattern>,
+}
+
+/// A regular expression compiled when the configuration is loaded.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map(Pattern).map_err(D::Error::custom)
+    }
+}
+
+impl RequestMatcher {
+    pub fn matches(&self, request: &MockRequest) -> bool {
+        let query_matches = self
+            .query
+            .iter()
+            .all(|(name, expected)| request.query.get(name) == Some(expected));
+        let headers_match = self
+            .headers
+            .iter()
+            .all(|(name, expected)| request.header(name) == Some(expected.as_str()));
+        let body_matches = self
+            .body
+            .as_ref()
+            .is_none_or(|body| body.matches(&request.body));
+
+        query_matches && headers_match && body_matches
+    }
+}
+
+impl BodyMatcher {
+    pub fn matches(&self, body: &[u8]) -> bool {
+        if let Some(pattern) = &self.matches
+            && !pattern.is_match(&String::from_utf8_lossy(body)) {
+            return false;
+        }
+
+        let needs_json = self.equals_json.is_some() || self.contains_json.is_some() || self.json_path.is_some();
+        if !needs_json {
+            return true;
+        }
+
+        let Ok(actual) = serde_json::from_slice::<Value>(body) else {
+            return false;
+        };
+
+        self.equals_json.as_ref().is_none_or(|expected| &actual == expected)
+            && self.contains_json.as_ref().is_none_or(|expected| contains_json(&actual, expected))
+            && self.json_path.as_ref().is_none_or(|path| !path.query(&actual).is_empty())
+    }
+}
+
+/// Checks that every field and array element of `expected` is present in `actual`.
+fn contains_json(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual.get(key).is_some_and(|actual| contains_json(actual, value))
+        }),
+        (Value::Array(actual), Value::Array(expected)) => expected
+            .iter()
+            .all(|value| actual.iter().any(|actual| contains_json(actual, value))),
+        _ => actual == expected,
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use anyhow::anyhow;
+use base64::{Engine as _, engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}};
+use jsonwebtoken::{encode, get_current_timestamp, Algorithm, DecodingKey, EncodingKey, Header};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tracing::info;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+use crate::cors;
+use crate::handler::MockState;
+
+/// Paths served by the issuer, which endpoints cannot use while `oauth` is configured
+pub const OAUTH_PATHS: [&str; 3] = ["/.well-known/openid-configuration", "/.well-known/jwks.json", "/token"];
+const KEY_ID: &str = "mockserver";
+
+/// The top-level `oauth` section: a local OAuth2 / OpenID Connect issuer minting ES256 tokens.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OAuthConfig {
+    /// `iss` of the minted tokens, `http://{Host header}` when omitted
+    pub issuer: Option<String>,
+    /// `aud` of the minted tokens
+    pub audience: Option<String>,
+    /// Token lifetime in seconds, one hour by default
+    #[serde(default = "default_expires_in")]
+    pub expires_in: u64,
+    #[serde(default)]
+    pub clients: Vec<OAuthClient>,
+    #[serde(default)]
+    pub users: Vec<OAuthUser>,
+}
+
+fn default_expires_in() -> u64 {
+    3600
+}
+
+/// A client allowed to use the `client_credentials` grant.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OAuthClient {
+    pub client_id: String,
+    /// Public clients have no secret
+    pub client_secret: Option<String>,
+    /// Space-delimited scopes the client may request, all of them when it requests none
+    pub scope: Option<String>,
+    /// Extra claims added to its tokens
+    #[serde(default)]
+    pub claims: Map<String, Value>,
+}
+
+/// A user allowed to use the `password` grant.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OAuthUser {
+    pub username: String,
+    pub password: String,
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub claims: Map<String, Value>,
+}
+
+/// The issuer and its signing key, generated at startup.
+#[derive(Clone)]
+pub struct Issuer {
+    config: Arc<OAuthConfig>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Value,
+}
+
+type TokenError = (StatusCode, Value);
+
+impl Issuer {
+    pub fn new(config: OAuthConfig) -> anyhow::Result<Self> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .map_err(|_| anyhow!("Cannot generate the OAuth signing key"))?;
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .map_err(|_| anyhow!("Cannot load the OAuth signing key"))?;
+
+        // Uncompressed point: 0x04, then the 32-byte x and y coordinates
+        let public_key = key_pair.public_key().as_ref();
+        let x = URL_SAFE_NO_PAD.encode(&public_key[1..33]);
+        let y = URL_SAFE_NO_PAD.encode(&public_key[33..]);
+
+        Ok(Issuer {
+            config: Arc::new(config),
+            encoding_key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+            decoding_key: DecodingKey::from_ec_components(&x, &y)?,
+            jwk: json!({"kty": "EC", "crv": "P-256", "x": x, "y": y, "use": "sig", "alg": "ES256", "kid": KEY_ID}),
+        })
+    }
+
+    /// Key verifying the tokens minted by this issuer.
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    fn issuer_url(&self, host: Option<&str>) -> String {
+        match &self.config.issuer {
+            Some(issuer) => issuer.trim_end_matches('/').to_string(),
+            None => format!("http://{}", host.unwrap_or("localhost")),
+        }
+    }
+
+    fn discovery(&self, host: Option<&str>) -> Value {
+        let issuer = self.issuer_url(host);
+        let mut scopes: Vec<&str> = self
+            .config
+            .clients
+            .iter()
+            .filter_map(|client| client.scope.as_deref())
+            .chain(self.config.users.iter().filter_map(|user| user.scope.as_deref()))
+            .flat_map(str::split_whitespace)
+            .collect();
+        scopes.sort_unstable();
+        scopes.dedup();
+
+        json!({
+            "issuer": issuer,
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "grant_types_supported": ["client_credentials", "password"],
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+            "response_types_supported": ["token"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["ES256"],
+            "scopes_supported": scopes,
+        })
+    }
+
+    fn jwks(&self) -> Value {
+        json!({"keys": [self.jwk]})
+    }
+
+    /// Handles a token request, following RFC 6749 for both the response and the errors.
+    fn token(&self, host: Option<&str>, authorization: Option<&str>, form: &HashMap<String, String>) -> Result<Value, TokenError> {
+        let client = self.authenticate_client(authorization, form)?;
+
+        match form.get("grant_type").map(String::as_str) {
+            Some("client_credentials") => {
+                let client = client.ok_or_else(|| token_error(StatusCode::UNAUTHORIZED, "invalid_client", "Client authentication is required"))?;
+                let scope = granted_scope(client.scope.as_deref(), form.get("scope"))?;
+                info!("🔑 Issuing token to client {}", client.client_id);
+                Ok(self.mint(host, &client.client_id, scope, &client.claims, Some(&client.client_id)))
+            }
+            Some("password") => {
+                let user = self
+                    .config
+                    .users
+                    .iter()
+                    .find(|user| form.get("username") == Some(&user.username) && form.get("password") == Some(&user.password))
+                    .ok_or_else(|| token_error(StatusCode::BAD_REQUEST, "invalid_grant", "Invalid username or password"))?;
+                let scope = granted_scope(user.scope.as_deref(), form.get("scope"))?;
+                info!("🔑 Issuing token to user {}", user.username);
+                Ok(self.mint(host, &user.username, scope, &user.claims, client.map(|client| client.client_id.as_str())))
+            }
+            Some(grant_type) => Err(token_error(
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+                &format!("Grant type '{}' is not supported", grant_type),
+            )),
+            None => Err(token_error(StatusCode::BAD_REQUEST, "invalid_request", "Missing grant_type")),
+        }
+    }
+
+    /// Reads client credentials from a Basic `Authorization` header or from the form; none is fine for public clients.
+    fn authenticate_client(&self, authorization: Option<&str>, form: &HashMap<String, String>) -> Result<Option<&OAuthClient>, TokenError> {
+        let basic = authorization
+            .and_then(|header| header.strip_prefix("Basic "))
+            .and_then(|encoded| STANDARD.decode(encoded).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .and_then(|decoded| decoded.split_once(':').map(|(id, secret)| (id.to_string(), Some(secret.to_string()))));
+        let (client_id, client_secret) = match basic {
+            Some(credentials) => credentials,
+            None => match form.get("client_id") {
+                Some(client_id) => (client_id.clone(), form.get("client_secret").cloned()),
+                None => return Ok(None),
+            },
+        };
+
+        self.config
+            .clients
+            .iter()
+            .find(|client| client.client_id == client_id && (client.client_secret.is_none() || client.client_secret == client_secret))
+            .map(Some)
+            .ok_or_else(|| token_error(StatusCode::UNAUTHORIZED, "invalid_client", "Unknown client or wrong secret"))
+    }
+
+    fn mint(&self, host: Option<&str>, subject: &str, scope: Option<String>, claims: &Map<String, Value>, client_id: Option<&str>) -> Value {
+        let now = get_current_timestamp();
+        let mut payload = Map::new();
+        payload.insert("iss".to_string(), json!(self.issuer_url(host)));
+        payload.insert("sub".to_string(), json!(subject));
+        payload.insert("iat".to_string(), json!(now));
+        payload.insert("exp".to_string(), json!(now + self.config.expires_in));
+        payload.insert("jti".to_string(), json!(Uuid::new_v4().to_string()));
+        if let Some(audience) = &self.config.audience {
+            payload.insert("aud".to_string(), json!(audience));
+        }
+        if let Some(client_id) = client_id {
+            payload.insert("client_id".to_string(), json!(client_id));
+        }
+        if let Some(scope) = &scope {
+            payload.insert("scope".to_string(), json!(scope));
+        }
+        payload.extend(claims.clone());
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(KEY_ID.to_string());
+        let access_token = encode(&header, &payload, &self.encoding_key).expect("Signing with a generated key cannot fail");
+
+        let mut response = json!({
+            "access_token": access_token,
+            "token_type": "Bearer",
+            "expires_in": self.config.expires_in,
+        });
+        if let Some(scope) = scope {
+            response["scope"] = json!(scope);
+        }
+        response
+    }
+}
+
+/// The requested scopes, when all of them are allowed, or every allowed scope when none is requested.
+fn granted_scope(allowed: Option<&str>, requested: Option<&String>) -> Result<Option<String>, TokenError> {
+    let Some(requested) = requested.filter(|requested| !requested.trim().is_empty()) else {
+        return Ok(allowed.map(str::to_string));
+    };
+    if let Some(allowed) = allowed
+        && let Some(scope) = requested.split_whitespace().find(|scope| !allowed.split_whitespace().any(|a| a == *scope)) {
+        return Err(token_error(StatusCode::BAD_REQUEST, "invalid_scope", &format!("Scope '{}' is not allowed", scope)));
+    }
+
+    Ok(Some(requested.split_whitespace().collect::<Vec<_>>().join(" ")))
+}
+
+fn token_error(status: StatusCode, error: &str, description: &str) -> TokenError {
+    info!("❌ Token request rejected: {}", description);
+    (status, json!({"error": error, "error_description": description}))
+}
+
+/// Issuer endpoints, served when the configuration has an `oauth` section:
+/// - `GET /.well-known/openid-configuration`: the discovery document.
+/// - `GET /.well-known/jwks.json`: the public key of the tokens.
+/// - `POST /token`: `client_credentials` and `password` grants, with form-encoded parameters.
+///
+/// They follow the top-level `cors` section, so a single-page app can fetch tokens.
+pub fn routes(state: MockState) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let cors = state.cors.clone();
+    let issuer = warp::any().and_then(move || {
+        let issuer = state.issuer.clone();
+        async move { issuer.ok_or_else(warp::reject::not_found) }
+    });
+    let host = warp::header::optional::<String>("host");
+
+    let discovery = warp::path!(".well-known" / "openid-configuration")
+        .and(warp::get())
+        .and(host)
+        .and(issuer.clone())
+        .map(|host: Option<String>, issuer: Issuer| warp::reply::json(&issuer.discovery(host.as_deref())).into_response());
+
+    let jwks = warp::path!(".well-known" / "jwks.json")
+        .and(warp::get())
+        .and(issuer.clone())
+        .map(|issuer: Issuer| warp::reply::json(&issuer.jwks()).into_response());
+
+    let token = warp::path!("token")
+        .and(warp::post())
+        // Checked before the body is read, so a `/token` endpoint of the mock can still read it
+        .and(issuer.clone())
+        .and(host)
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::form::<HashMap<String, String>>())
+        .map(|issuer: Issuer, host: Option<String>, authorization: Option<String>, form: HashMap<String, String>| {
+            let (status, body) = match issuer.token(host.as_deref(), authorization.as_deref(), &form) {
+                Ok(body) => (StatusCode::OK, body),
+                Err(error) => error,
+            };
+            let reply = warp::reply::with_status(warp::reply::json(&body), status);
+            warp::reply::with_header(reply, "Cache-Control", "no-store").into_response()
+        });
+
+    let paths = warp::path!(".well-known" / "openid-configuration")
+        .or(warp::path!(".well-known" / "jwks.json"))
+        .unify()
+        .or(warp::path!("token"))
+        .unify()
+        .and(issuer)
+        .map(|_| ())
+        .untuple_one();
+    let routes = discovery.or(jwks).unify().or(token).unify();
+    cors::serve(cors, paths, routes)
+}
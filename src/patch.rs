@@ -0,0 +1,57 @@
+use bytes::Bytes;
+use json_patch::{Patch, merge, patch};
+use serde_json::Value;
+use warp::http::Response;
+
+pub const JSON_PATCH: &str = "application/json-patch+json";
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+
+/// A patch that could not be applied, with the status explaining why.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatchError {
+    pub status_code: u16,
+    pub message: String,
+}
+
+impl PatchError {
+    fn new(status_code: u16, message: impl Into<String>) -> Self {
+        PatchError { status_code, message: message.into() }
+    }
+
+    pub fn response(&self) -> Response<Bytes> {
+        Response::builder()
+            .status(self.status_code)
+            .body(format!("{}\n", self.message).into())
+            .unwrap()
+    }
+}
+
+/// Applies a PATCH request body to `document`.
+///
+/// `application/json-patch+json` bodies are RFC 6902 JSON Patch documents, applied atomically.
+/// `application/merge-patch+json` and plain `application/json` bodies (or no `Content-Type`)
+/// are RFC 7386 merge patches.
+pub fn apply_patch(document: &mut Value, content_type: Option<&str>, body: &[u8]) -> Result<(), PatchError> {
+    let media_type = content_type
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    match media_type.as_deref() {
+        Some(JSON_PATCH) => {
+            let operations: Patch = serde_json::from_slice(body)
+                .map_err(|e| PatchError::new(400, format!("Invalid JSON Patch document: {}", e)))?;
+            patch(document, &operations)
+                .map_err(|e| PatchError::new(422, format!("JSON Patch failed: {}", e)))
+        }
+        None | Some(MERGE_PATCH) | Some("application/json") => {
+            let changes: Value = serde_json::from_slice(body)
+                .map_err(|e| PatchError::new(400, format!("Invalid merge patch document: {}", e)))?;
+            merge(document, &changes);
+            Ok(())
+        }
+        Some(other) => Err(PatchError::new(
+            415,
+            format!("Unsupported patch type '{}', use {} or {}", other, JSON_PATCH, MERGE_PATCH),
+        )),
+    }
+}
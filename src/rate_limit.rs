@@ -1,61 +1,355 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::bail;
+use bytes::Bytes;
 use serde::Deserialize;
+use serde_json::Value;
 use tokio::sync::Mutex;
+use warp::http::header::{AUTHORIZATION, RETRY_AFTER};
+use warp::http::{HeaderValue, Response};
 use warp::reject::Reject;
+use crate::config::Endpoint;
+use crate::jwt::unverified_claims;
+use crate::request::MockRequest;
 
-pub type RateLimitTracker = Arc<Mutex<HashMap<String, (Instant, usize)>>>;
+pub type RateLimitTracker = Arc<Mutex<HashMap<String, RateLimitState>>>;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct RateLimit {
     pub requests: usize, // Allowed requests per window
     pub window_ms: u64,  // Time window in milliseconds
+    /// Which clients share a counter, all of them by default
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub key_by: RateLimitKey,
+    #[serde(default)]
+    pub algorithm: RateLimitAlgorithm,
+    /// Tokens a `token_bucket` holds, so the requests it lets through at once; `requests` by default
+    pub burst: Option<usize>,
+    /// Adds the IETF draft `RateLimit-*` headers to the `X-RateLimit-*` ones
+    #[serde(default)]
+    pub ietf_headers: bool,
+}
+
+/// How requests are counted against `requests` per `window_ms`.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// Counts requests in windows starting with the first request after the previous one ended
+    #[default]
+    FixedWindow,
+    /// Remembers every allowed request of the last `window_ms`
+    SlidingLog,
+    /// Sliding window counter: weighs the previous window's count by its overlap with the last `window_ms`
+    SlidingWindow,
+    /// Refills `requests` tokens per `window_ms`, up to `burst`; each request takes one
+    TokenBucket,
+}
+
+/// What a [`RateLimit`] remembers about one counter.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitState(Option<Counter>);
+
+#[derive(Debug, Clone)]
+enum Counter {
+    FixedWindow { start: Instant, count: usize },
+    SlidingLog(VecDeque<Instant>),
+    SlidingWindow { start: Instant, previous: usize, current: usize },
+    TokenBucket { tokens: f64, updated: Instant },
+}
+
+/// What identifies a client for rate limiting, written `global`, `ip`, `header: X-Api-Key` or `claim: sub`.
+///
+/// Requests lacking the header or claim share one counter.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    #[default]
+    Global,
+    /// The client IP, taken from `X-Forwarded-For` when present
+    Ip,
+    Header(String),
+    /// A claim of the bearer token, read without verifying it
+    Claim(String),
+}
+
+impl RateLimitKey {
+    fn client(&self, request: &MockRequest) -> Option<String> {
+        let client = match self {
+            RateLimitKey::Global => return None,
+            RateLimitKey::Ip => request.client_ip().map(|ip| ip.to_string()),
+            RateLimitKey::Header(name) => request.header(name).map(str::to_string),
+            RateLimitKey::Claim(name) => request
+                .header(AUTHORIZATION.as_str())
+                .and_then(|header| header.strip_prefix("Bearer "))
+                .and_then(unverified_claims)
+                .and_then(|claims| match claims.get(name)? {
+                    Value::String(value) => Some(value.clone()),
+                    value => Some(value.to_string()),
+                }),
+        };
+        Some(client.unwrap_or_default())
+    }
+}
+
+/// Where a counter stands after a request, reported in the response headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: usize,
+    pub remaining: usize,
+    /// Time until the counter is back to its full quota, or the current window ends
+    pub reset: Duration,
+    /// Time until a request would be allowed, zero while some remain
+    pub retry_after: Duration,
+    window_ms: u64,
+    ietf_headers: bool,
+}
+
+impl RateLimitStatus {
+    /// Adds `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` (a Unix time in seconds),
+    /// `Retry-After` when throttled, and the `RateLimit-*` headers when enabled.
+    pub fn apply(&self, response: &mut Response<Bytes>) {
+        let reset_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() + self.reset;
+        let mut headers = vec![
+            ("X-RateLimit-Limit", self.limit.to_string()),
+            ("X-RateLimit-Remaining", self.remaining.to_string()),
+            ("X-RateLimit-Reset", whole_seconds(reset_at).to_string()),
+        ];
+        if self.ietf_headers {
+            headers.push(("RateLimit-Limit", self.limit.to_string()));
+            headers.push(("RateLimit-Remaining", self.remaining.to_string()));
+            headers.push(("RateLimit-Reset", whole_seconds(self.reset).to_string()));
+            headers.push(("RateLimit-Policy", format!("{};w={}", self.limit, whole_seconds(Duration::from_millis(self.window_ms)))));
+        }
+        if !self.allowed {
+            headers.push((RETRY_AFTER.as_str(), whole_seconds(self.retry_after).max(1).to_string()));
+        }
+
+        for (name, value) in headers {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+}
+
+/// Seconds, rounded up so clients never retry too early.
+fn whole_seconds(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
 }
 
 #[derive(Debug)]
-pub struct RateLimited;
+pub struct RateLimited(pub RateLimitStatus);
 impl Reject for RateLimited {}
 
+impl RateLimit {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.window_ms == 0 {
+            bail!("'window_ms' must be positive");
+        }
+        if self.requests == 0 && self.algorithm == RateLimitAlgorithm::TokenBucket {
+            bail!("'requests' must be positive to refill a 'token_bucket'");
+        }
+        if self.burst.is_some() && self.algorithm != RateLimitAlgorithm::TokenBucket {
+            bail!("'burst' is only used by the 'token_bucket' algorithm");
+        }
+
+        Ok(())
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms)
+    }
+
+    /// Counts a request made at `now`, returning whether it is allowed and what remains.
+    pub fn acquire(&self, state: &mut RateLimitState, now: Instant) -> RateLimitStatus {
+        let window = self.window();
+        let requests = self.requests;
+        let counter = state.0.get_or_insert_with(|| self.algorithm.counter(self, now));
+
+        let (allowed, remaining, reset, retry_after) = match counter {
+            Counter::FixedWindow { start, count } => {
+                if now.duration_since(*start) > window {
+                    *start = now;
+                    *count = 0;
+                }
+                *count += 1;
+                let reset = window.saturating_sub(now.duration_since(*start));
+                (*count <= requests, requests.saturating_sub(*count), reset, reset)
+            }
+            Counter::SlidingLog(log) => {
+                while log.front().is_some_and(|allowed| now.duration_since(*allowed) >= window) {
+                    log.pop_front();
+                }
+                let allowed = log.len() < requests;
+                if allowed {
+                    log.push_back(now);
+                }
+                // A slot frees up when the oldest request leaves the window
+                let reset = log.front().map_or(Duration::ZERO, |oldest| window.saturating_sub(now.duration_since(*oldest)));
+                (allowed, requests - log.len(), reset, reset)
+            }
+            Counter::SlidingWindow { start, previous, current } => {
+                let elapsed = now.duration_since(*start).as_millis();
+                let elapsed_windows = elapsed / window.as_millis();
+                if elapsed_windows > 0 {
+                    *previous = if elapsed_windows == 1 { *current } else { 0 };
+                    *current = 0;
+                    *start = now - Duration::from_millis((elapsed % window.as_millis()) as u64);
+                }
+                let into_window = now.duration_since(*start);
+                let overlap = 1.0 - into_window.as_secs_f64() / window.as_secs_f64();
+                let estimate = |current: usize| *previous as f64 * overlap + current as f64;
+                let allowed = estimate(*current) < requests as f64;
+                if allowed {
+                    *current += 1;
+                }
+                let remaining = (requests as f64 - estimate(*current)).max(0.0) as usize;
+                let reset = window - into_window;
+                (allowed, remaining, reset, sliding_window_retry(window, into_window, *previous, *current, requests))
+            }
+            Counter::TokenBucket { tokens, updated } => {
+                let burst = self.burst.unwrap_or(requests) as f64;
+                let refill_rate = requests as f64 / window.as_secs_f64();
+                *tokens = (*tokens + now.duration_since(*updated).as_secs_f64() * refill_rate).min(burst);
+                *updated = now;
+                let allowed = *tokens >= 1.0;
+                if allowed {
+                    *tokens -= 1.0;
+                }
+                let reset = Duration::from_secs_f64((burst - *tokens) / refill_rate);
+                let retry_after = Duration::from_secs_f64((1.0 - *tokens).max(0.0) / refill_rate);
+                (allowed, *tokens as usize, reset, retry_after)
+            }
+        };
+
+        RateLimitStatus {
+            allowed,
+            limit: requests,
+            remaining,
+            reset,
+            retry_after: if remaining > 0 { Duration::ZERO } else { retry_after },
+            window_ms: self.window_ms,
+            ietf_headers: self.ietf_headers,
+        }
+    }
+}
+
+/// Time until the weighted count of a sliding window drops below the limit again.
+fn sliding_window_retry(window: Duration, into_window: Duration, previous: usize, current: usize, requests: usize) -> Duration {
+    let window_secs = window.as_secs_f64();
+    let requests = requests as f64;
+    // Within this window, the previous count must fade below the room left by the current one
+    if (current as f64) < requests {
+        let fade = window_secs * (1.0 - (requests - current as f64) / previous as f64);
+        return Duration::from_secs_f64((fade - into_window.as_secs_f64()).max(0.0));
+    }
+    // Otherwise the current count becomes the previous one, and must fade in the next window
+    let fade = window_secs * (1.0 - requests / current as f64);
+    window - into_window + Duration::from_secs_f64(fade.max(0.0))
+}
+
+impl RateLimitAlgorithm {
+    fn counter(self, limit: &RateLimit, now: Instant) -> Counter {
+        match self {
+            RateLimitAlgorithm::FixedWindow => Counter::FixedWindow { start: now, count: 0 },
+            RateLimitAlgorithm::SlidingLog => Counter::SlidingLog(VecDeque::new()),
+            RateLimitAlgorithm::SlidingWindow => Counter::SlidingWindow { start: now, previous: 0, current: 0 },
+            RateLimitAlgorithm::TokenBucket => Counter::TokenBucket {
+                tokens: limit.burst.unwrap_or(limit.requests) as f64,
+                updated: now,
+            },
+        }
+    }
+}
+
 /// Initialize the rate limit tracker
 pub fn new_rate_limit() -> RateLimitTracker {
     Arc::new(Mutex::new(HashMap::new()))
 }
 
+/// Limits shared by several endpoints: one over the whole server, and named groups.
+#[derive(Debug, Clone, Default)]
+pub struct SharedRateLimits {
+    pub server: Option<RateLimit>,
+    pub groups: HashMap<String, RateLimit>,
+}
+
+impl SharedRateLimits {
+    /// The limits a request to an endpoint counts against, each with the name of its counter:
+    /// the endpoint's for this method, its group's and the server's.
+    pub fn applicable<'a>(&'a self, path: &str, request: &MockRequest, endpoint: &'a Endpoint) -> Vec<(String, &'a RateLimit)> {
+        let mut limits = Vec::new();
+        if let Some(limit) = &endpoint.rate_limit {
+            limits.push((format!("{}|{}", path, request.method), limit));
+        }
+        if let Some(group) = &endpoint.rate_limit_group
+            && let Some(limit) = self.groups.get(group) {
+            limits.push((format!("group:{}", group), limit));
+        }
+        if let Some(limit) = &self.server {
+            limits.push(("server".to_string(), limit));
+        }
+        limits
+    }
+}
+
+/// Counts the request against every applicable limit, returning the most restrictive status for the response headers.
+///
+/// A request turned down by one limit is not counted by the others.
 pub async fn check_rate_limit(
-    path: String,
-    method: &str,
-    rate_limit: Option<&RateLimit>,
+    limits: &[(String, &RateLimit)],
+    request: &MockRequest,
     rate_limiter: RateLimitTracker,
-) -> Result<(), warp::Rejection> {
-    if let Some(limit) = rate_limit {
-        let mut rate_tracker = rate_limiter.lock().await;
-
-        // Use both path and method as the key for rate limiting
-        let key = format!("{}|{}", path, method); // Combine path and method
-
-        let now = Instant::now();
-        let (start_time, count) = rate_tracker.entry(key.clone()).or_insert((now, 0));
-
-        if now.duration_since(*start_time).as_millis() as u64 > limit.window_ms {
-            *start_time = now;
-            *count = 1;
-            tracing::info!("🕛 Rate window expired for path: {}. Resetting counter.", key);
-        } else {
-            *count += 1;
-
-            if *count > limit.requests {
-                tracing::warn!(
-                    "⚠️ Rate limit exceeded for path: {} | Method: {} | Current count: {} | Limit: {}",
-                    path,
-                    method,
-                    *count,
-                    limit.requests
-                );
-                return Err(warp::reject::custom(RateLimited));
-            }
+) -> Result<Option<RateLimitStatus>, warp::Rejection> {
+    if limits.is_empty() {
+        return Ok(None);
+    }
+
+    let mut rate_tracker = rate_limiter.lock().await;
+    let now = Instant::now();
+    let counted: Vec<_> = limits
+        .iter()
+        .map(|(scope, limit)| {
+            // Limits per client count each client separately
+            let key = match limit.key_by.client(request) {
+                Some(client) => format!("{}|{}", scope, client),
+                None => scope.clone(),
+            };
+            let mut state = rate_tracker.get(&key).cloned().unwrap_or_default();
+            let status = limit.acquire(&mut state, now);
+            (key, limit, state, status)
+        })
+        .collect();
+
+    let denied = counted.iter().any(|(.., status)| !status.allowed);
+    let mut statuses = Vec::new();
+    for (key, limit, state, status) in counted {
+        if !status.allowed {
+            tracing::warn!(
+                "⚠️ Rate limit exceeded for {} {} | Counter: {} | Limit: {} per {} ms ({:?})",
+                request.method,
+                request.path,
+                key,
+                limit.requests,
+                limit.window_ms,
+                limit.algorithm
+            );
+        }
+        if !denied || !status.allowed {
+            rate_tracker.insert(key, state);
         }
+        statuses.push(status);
     }
-    
-    Ok(())
-}
\ No newline at end of file
+
+    let status = statuses
+        .into_iter()
+        .min_by_key(|status| (status.allowed, Reverse(status.retry_after), status.remaining))
+        .expect("At least one limit applies");
+    if denied {
+        return Err(warp::reject::custom(RateLimited(status)));
+    }
+    Ok(Some(status))
+}
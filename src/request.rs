@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use bytes::Bytes;
+use warp::http::header::COOKIE;
+use warp::http::{HeaderMap, Method};
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+/// The parts of an incoming request that endpoints can be matched and rendered against.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    /// Address of the peer, unknown for requests that do not come from a socket
+    pub remote_addr: Option<SocketAddr>,
+}
+
+impl MockRequest {
+    /// Returns the value of a header as a string, ignoring the case of the name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// The client's IP address: the first `X-Forwarded-For` entry when behind a proxy, else the peer's address.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.header("X-Forwarded-For")
+            .and_then(|forwarded| forwarded.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .or(self.remote_addr.map(|addr| addr.ip()))
+    }
+
+    /// Returns the value of a cookie sent in the `Cookie` headers.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find_map(|(cookie_name, value)| (cookie_name == name).then_some(value))
+    }
+}
+
+/// Extracts a [`MockRequest`] from any incoming request.
+pub fn extract() -> impl Filter<Extract = (MockRequest,), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::method())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and(warp::addr::remote())
+        .map(|path: FullPath, method, query, headers, body, remote_addr| MockRequest {
+            method,
+            path: path.as_str().to_string(),
+            query,
+            headers,
+            body,
+            remote_addr,
+        })
+}
@@ -0,0 +1,325 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+use tracing::info;
+use uuid::Uuid;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::Response;
+use crate::patch::apply_patch;
+use crate::request::MockRequest;
+use crate::router::RouteMatch;
+
+/// A collection of JSON objects served as a REST API, like json-server.
+///
+/// The endpoint key serves the collection (`GET` lists, `POST` creates) and the same key
+/// followed by `/{id}` serves a single item (`GET`, `PUT`, `PATCH`, `DELETE`).
+/// `PATCH` accepts both JSON Merge Patch and JSON Patch documents, see [`apply_patch`].
+#[derive(Debug, Deserialize, Clone)]
+pub struct Resource {
+    /// JSON file holding an array with every item
+    pub file: Option<String>,
+    /// Directory holding one `{id}.json` file per item
+    pub directory: Option<String>,
+    /// Field identifying an item, `id` when omitted
+    #[serde(default = "default_id_field")]
+    pub id_field: String,
+}
+
+fn default_id_field() -> String {
+    "id".to_string()
+}
+
+impl Resource {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match (&self.file, &self.directory) {
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => bail!("Exactly one of 'file' or 'directory' is required"),
+        }
+        if self.id_field.is_empty() {
+            bail!("'id_field' cannot be empty");
+        }
+
+        Ok(())
+    }
+
+    /// Whether the collection route, or the item route, answers `method`.
+    pub fn serves(&self, route: &RouteMatch<'_>, method: &str) -> bool {
+        let methods: &[&str] = if route.params.contains_key(&self.id_field) {
+            &["GET", "HEAD", "PUT", "PATCH", "DELETE", "OPTIONS"]
+        } else {
+            &["GET", "HEAD", "POST", "OPTIONS"]
+        };
+        methods.contains(&method)
+    }
+
+    fn storage(&self, route: &RouteMatch<'_>, responses_folder: &str) -> Storage {
+        match (&self.file, &self.directory) {
+            (Some(file), _) => Storage::File(Path::new(responses_folder).join(route.expand(file))),
+            (None, Some(directory)) => Storage::Directory(Path::new(responses_folder).join(route.expand(directory))),
+            (None, None) => unreachable!("resources are validated when the configuration is loaded"),
+        }
+    }
+}
+
+enum Storage {
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+impl Storage {
+    /// Reads every item; a missing file or directory is an empty collection.
+    fn load(&self, id_field: &str) -> anyhow::Result<Vec<Value>> {
+        match self {
+            Storage::File(path) => {
+                if !path.exists() {
+                    return Ok(Vec::new());
+                }
+                let contents = fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+                serde_json::from_slice(&contents).with_context(|| format!("{} is not a JSON array", path.display()))
+            }
+            Storage::Directory(path) => {
+                if !path.exists() {
+                    return Ok(Vec::new());
+                }
+                let mut items: Vec<Value> = Vec::new();
+                for entry in fs::read_dir(path)? {
+                    let file = entry?.path();
+                    if file.extension().is_some_and(|ext| ext == "json") {
+                        let contents = fs::read(&file)?;
+                        items.push(serde_json::from_slice(&contents)
+                            .with_context(|| format!("{} is not valid JSON", file.display()))?);
+                    }
+                }
+                items.sort_by(|a, b| compare_ids(&a[id_field], &b[id_field]));
+                Ok(items)
+            }
+        }
+    }
+
+    fn save(&self, items: &[Value], id_field: &str) -> anyhow::Result<()> {
+        match self {
+            Storage::File(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, serde_json::to_vec_pretty(items)?)?;
+            }
+            Storage::Directory(path) => {
+                fs::create_dir_all(path)?;
+                let mut kept = Vec::with_capacity(items.len());
+                for item in items {
+                    let id = id_string(&item[id_field]).context("Item without an id")?;
+                    let file_name = format!("{}.json", id);
+                    fs::write(path.join(&file_name), serde_json::to_vec_pretty(item)?)?;
+                    kept.push(file_name);
+                }
+                for entry in fs::read_dir(path)? {
+                    let file = entry?.path();
+                    let is_stale = file.extension().is_some_and(|ext| ext == "json")
+                        && file.file_name().and_then(|name| name.to_str()).is_some_and(|name| !kept.iter().any(|k| k == name));
+                    if is_stale {
+                        fs::remove_file(file)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes reads and writes of resource collections.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceStore {
+    lock: Arc<Mutex<()>>,
+}
+
+impl ResourceStore {
+    pub async fn handle(
+        &self,
+        resource: &Resource,
+        route: &RouteMatch<'_>,
+        request: &MockRequest,
+        responses_folder: &str,
+    ) -> Response<Bytes> {
+        let _guard = self.lock.lock().await;
+        let storage = resource.storage(route, responses_folder);
+        let id = route.params.get(&resource.id_field).cloned();
+
+        // Items are read and written with blocking calls, kept off the async workers
+        let task = {
+            let (resource, request) = (resource.clone(), request.clone());
+            tokio::task::spawn_blocking(move || apply(&resource, &storage, id.as_deref(), &request))
+        };
+        match task.await.context("Resource task failed").and_then(|result| result) {
+            Ok(response) => response,
+            Err(error) => {
+                info!("🚫 Resource error for {}: {:#}", request.path, error);
+                text_response(500, format!("Resource error: {:#}\n", error))
+            }
+        }
+    }
+}
+
+fn apply(
+    resource: &Resource,
+    storage: &Storage,
+    id: Option<&str>,
+    request: &MockRequest,
+) -> anyhow::Result<Response<Bytes>> {
+    let id_field = resource.id_field.as_str();
+    let mut items = storage.load(id_field)?;
+    let position = id.and_then(|id| items.iter().position(|item| id_string(&item[id_field]).as_deref() == Some(id)));
+
+    let response = match (request.method.as_str(), id, position) {
+        ("GET" | "HEAD", None, _) => {
+            // Parameters that no item has as a field, such as cache busters, do not filter
+            let filters: Vec<(&String, &String)> = request
+                .query
+                .iter()
+                .filter(|(field, _)| items.iter().any(|item| item.get(field.as_str()).is_some()))
+                .collect();
+            let filtered: Vec<&Value> = items
+                .iter()
+                .filter(|item| filters.iter().all(|(field, value)| field_matches(&item[field.as_str()], value)))
+                .collect();
+            json_response(200, &filtered)
+        }
+        ("POST", None, _) => {
+            let Some(mut item) = json_object(&request.body) else {
+                return Ok(text_response(400, "Expected a JSON object\n"));
+            };
+            let new_id = match item.get(id_field).filter(|value| !value.is_null()) {
+                Some(value) => value.clone(),
+                None => next_id(&items, id_field),
+            };
+            let Some(new_id_str) = id_string(&new_id).filter(|id| is_valid_id(id)) else {
+                return Ok(text_response(400, format!("Invalid '{}'\n", id_field)));
+            };
+            if items.iter().any(|existing| id_string(&existing[id_field]).as_deref() == Some(new_id_str.as_str())) {
+                return Ok(text_response(409, format!("Item '{}' already exists\n", new_id_str)));
+            }
+
+            item.insert(id_field.to_string(), new_id);
+            let item = Value::Object(item);
+            let mut response = json_response(201, &item);
+            items.push(item);
+            storage.save(&items, id_field)?;
+            info!("🗃️ Created item {} in {}", new_id_str, request.path);
+
+            let location = format!("{}/{}", request.path.trim_end_matches('/'), new_id_str);
+            if let Ok(location) = location.parse() {
+                response.headers_mut().insert("Location", location);
+            }
+            response
+        }
+        ("GET" | "HEAD" | "PUT" | "PATCH" | "DELETE", Some(_), None) => text_response(404, "Not Found\n"),
+        ("GET" | "HEAD", Some(_), Some(index)) => json_response(200, &items[index]),
+        ("PUT", Some(_), Some(index)) => {
+            let Some(item) = json_object(&request.body) else {
+                return Ok(text_response(400, "Expected a JSON object\n"));
+            };
+            replace_item(storage, &mut items, index, item, id_field)?
+        }
+        ("PATCH", Some(_), Some(index)) => {
+            let mut item = items[index].clone();
+            if let Err(error) = apply_patch(&mut item, request.header(CONTENT_TYPE.as_str()), &request.body) {
+                return Ok(error.response());
+            }
+            let Value::Object(item) = item else {
+                return Ok(text_response(422, "The patched item is not a JSON object\n"));
+            };
+            replace_item(storage, &mut items, index, item, id_field)?
+        }
+        ("DELETE", Some(id), Some(index)) => {
+            items.remove(index);
+            storage.save(&items, id_field)?;
+            info!("🗃️ Deleted item {} from {}", id, request.path);
+            text_response(204, "")
+        }
+        _ => text_response(405, "Method not allowed\n"),
+    };
+
+    Ok(response)
+}
+
+/// Stores `item` in place of the item at `index`, keeping its id: the id in the path wins over the body.
+fn replace_item(
+    storage: &Storage,
+    items: &mut [Value],
+    index: usize,
+    mut item: Map<String, Value>,
+    id_field: &str,
+) -> anyhow::Result<Response<Bytes>> {
+    item.insert(id_field.to_string(), items[index][id_field].clone());
+    items[index] = Value::Object(item);
+    storage.save(items, id_field)?;
+    Ok(json_response(200, &items[index]))
+}
+
+fn json_object(body: &[u8]) -> Option<Map<String, Value>> {
+    match serde_json::from_slice(body) {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+/// The textual form of an id, as it appears in the path.
+fn id_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Ids end up in paths and file names.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id != "." && id != ".." && !id.contains(['/', '\\'])
+}
+
+/// The next integer when every id is an integer, a random UUID otherwise.
+fn next_id(items: &[Value], id_field: &str) -> Value {
+    let ids: Option<Vec<i64>> = items.iter().map(|item| item[id_field].as_i64()).collect();
+    match ids {
+        Some(ids) => Value::from(ids.into_iter().max().unwrap_or(0) + 1),
+        None => Value::from(Uuid::new_v4().to_string()),
+    }
+}
+
+fn compare_ids(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => id_string(a).cmp(&id_string(b)),
+    }
+}
+
+/// Query parameters filter the collection on fields equal to the given value.
+fn field_matches(field: &Value, expected: &str) -> bool {
+    match field {
+        Value::String(value) => value == expected,
+        Value::Number(_) | Value::Bool(_) => serde_json::from_str::<Value>(expected).is_ok_and(|value| value == *field),
+        _ => false,
+    }
+}
+
+fn json_response(status_code: u16, value: &impl serde::Serialize) -> Response<Bytes> {
+    Response::builder()
+        .status(status_code)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(value).unwrap_or_default().into())
+        .unwrap()
+}
+
+fn text_response(status_code: u16, body: impl Into<Bytes>) -> Response<Bytes> {
+    Response::builder()
+        .status(status_code)
+        .body(body.into())
+        .unwrap()
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use bytes::Bytes;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use warp::http::{HeaderName, HeaderValue, Response};
+use crate::config::{BodySource, HeaderValues};
+use crate::router::RouteMatch;
+
+/// Infers the content type of a response file from its extension.
+///
+/// Files without an extension are served as JSON, like every response used to be.
+pub fn content_type(file_path: &str) -> &'static str {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        None | Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("css") => "text/css",
+        Some("yaml") | Some("yml") => "application/yaml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Adds configured headers to a response, replacing any header with the same name.
+///
+/// `{name}` placeholders in values are replaced with the captured path parameters.
+pub fn apply_headers(response: &mut Response<Bytes>, headers: &HashMap<String, HeaderValues>, route: &RouteMatch<'_>) {
+    for (name, values) in headers {
+        let Ok(name) = HeaderName::try_from(name.as_str()) else {
+            info!("⚠️ Skipping invalid header name {}", name);
+            continue;
+        };
+
+        response.headers_mut().remove(&name);
+        for value in values.as_slice() {
+            match HeaderValue::try_from(route.expand(value)) {
+                Ok(value) => {
+                    response.headers_mut().append(name.clone(), value);
+                }
+                Err(_) => info!("⚠️ Skipping invalid value for header {}: {}", name, value),
+            }
+        }
+    }
+}
+
+/// Serves the contents of a response file, or 404 when it cannot be read.
+///
+/// Files are read as bytes so images, PDFs or archives are served unchanged.
+pub fn file_response(
+    file_path: &str,
+    status_code: u16,
+    render: impl Fn(Vec<u8>) -> anyhow::Result<Vec<u8>>,
+) -> Response<Bytes> {
+    info!("📂 Fetching file from: {}", file_path);
+    if let Ok(contents) = fs::read(file_path) {
+        body_response(contents, content_type(file_path), status_code, render)
+    } else {
+        info!("🚫 File not found: {}", file_path);
+        Response::builder()
+            .status(404)
+            .body("Not Found\n".into())
+            .unwrap()
+    }
+}
+
+/// Serves a response body from a file in the responses folder or from the configuration.
+pub fn source_response(
+    source: BodySource<'_>,
+    route: &RouteMatch<'_>,
+    responses_folder: &str,
+    status_code: u16,
+    render: impl Fn(Vec<u8>) -> anyhow::Result<Vec<u8>>,
+) -> Response<Bytes> {
+    match source {
+        BodySource::File(file) => {
+            let file_path = format!("{}/{}", responses_folder, route.expand(file));
+            file_response(&file_path, status_code, render)
+        }
+        BodySource::Inline(body) => {
+            let content_type = if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+                "application/json"
+            } else {
+                "text/plain; charset=utf-8"
+            };
+            body_response(body.as_bytes().to_vec(), content_type, status_code, render)
+        }
+        BodySource::Json(json) => body_response(json.to_string().into_bytes(), "application/json", status_code, render),
+    }
+}
+
+fn body_response(
+    contents: Vec<u8>,
+    content_type: &str,
+    status_code: u16,
+    render: impl Fn(Vec<u8>) -> anyhow::Result<Vec<u8>>,
+) -> Response<Bytes> {
+    match render(contents) {
+        Ok(contents) => Response::builder()
+            .status(status_code)
+            .header("Content-Type", content_type)
+            .header("Content-Length", contents.len())
+            .body(contents.into())
+            .unwrap(),
+        Err(err) => {
+            info!("💥 Failed to render template: {}", err);
+            Response::builder()
+                .status(500)
+                .body(format!("Template error: {}\n", err).into())
+                .unwrap()
+        }
+    }
+}
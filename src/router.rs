@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use anyhow::bail;
+use crate::config::Endpoint;
+
+/// A single segment of a configured endpoint key such as `/api/user/{id}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+impl Segment {
+    /// Lower ranks are more specific and win when several routes match.
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Literal(_) => 0,
+            Segment::Param(_) => 1,
+            Segment::Wildcard(_) => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    pattern: String,
+    segments: Vec<Segment>,
+    endpoint: Endpoint,
+}
+
+/// Route table built from the endpoint keys of the configuration.
+///
+/// Keys may contain `{name}` segments, which capture a single path segment, and a
+/// trailing `*name` segment, which captures the remainder of the path (one or more segments;
+/// a bare `*` matches the same way without capturing).
+/// When several routes match, the one with the most specific segment at the first
+/// position where they differ wins (literal, then parameter, then wildcard).
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+/// The result of a successful lookup: the configured key, its endpoint and the captured values.
+#[derive(Debug)]
+pub struct RouteMatch<'a> {
+    pub pattern: &'a str,
+    pub endpoint: &'a Endpoint,
+    pub params: HashMap<String, String>,
+}
+
+impl Router {
+    pub fn new(endpoints: &HashMap<String, Endpoint>) -> anyhow::Result<Self> {
+        let mut routes = endpoints
+            .iter()
+            .map(|(pattern, endpoint)| {
+                Ok(Route {
+                    pattern: pattern.clone(),
+                    segments: parse_pattern(pattern)?,
+                    endpoint: endpoint.clone(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let items = routes
+            .iter()
+            .filter_map(|route| route.endpoint.resource.as_ref().map(|resource| item_route(route, &resource.id_field, endpoints)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        routes.extend(items);
+
+        routes.sort_by(|a, b| {
+            let a_ranks = a.segments.iter().map(Segment::rank);
+            let b_ranks = b.segments.iter().map(Segment::rank);
+            a_ranks.cmp(b_ranks).then_with(|| a.pattern.cmp(&b.pattern))
+        });
+
+        Ok(Router { routes })
+    }
+
+    /// Finds the highest-precedence route matching `path`.
+    pub fn find(&self, path: &str) -> Option<RouteMatch<'_>> {
+        let segments: Vec<&str> = split_path(path).collect();
+
+        self.routes.iter().find_map(|route| {
+            match_segments(&route.segments, &segments).map(|params| RouteMatch {
+                pattern: &route.pattern,
+                endpoint: &route.endpoint,
+                params,
+            })
+        })
+    }
+}
+
+impl RouteMatch<'_> {
+    /// Replaces `{name}` placeholders in `value` (e.g. a response file name) with captured values.
+    pub fn expand(&self, value: &str) -> String {
+        self.params.iter().fold(value.to_string(), |acc, (name, captured)| {
+            acc.replace(&format!("{{{}}}", name), captured)
+        })
+    }
+}
+
+/// The route serving a single item of a resource endpoint, i.e. its key followed by `/{id_field}`.
+fn item_route(collection: &Route, id_field: &str, endpoints: &HashMap<String, Endpoint>) -> anyhow::Result<Route> {
+    let pattern = format!("{}/{{{}}}", collection.pattern.trim_end_matches('/'), id_field);
+    if endpoints.contains_key(&pattern) {
+        bail!("'{}' is already served by the resource '{}'", pattern, collection.pattern);
+    }
+    for segment in &collection.segments {
+        match segment {
+            Segment::Wildcard(_) => bail!("Resource '{}' cannot end with a wildcard", collection.pattern),
+            Segment::Param(name) if name == id_field => {
+                bail!("Path parameter '{{{}}}' of '{}' clashes with the resource id field", name, collection.pattern)
+            }
+            _ => {}
+        }
+    }
+
+    let mut segments: Vec<Segment> = collection.segments.clone();
+    if segments.last() == Some(&Segment::Literal(String::new())) {
+        segments.pop();
+    }
+    segments.push(Segment::Param(id_field.to_string()));
+
+    Ok(Route { pattern, segments, endpoint: collection.endpoint.clone() })
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+fn parse_pattern(pattern: &str) -> anyhow::Result<Vec<Segment>> {
+    let raw: Vec<&str> = split_path(pattern).collect();
+    let mut segments = Vec::with_capacity(raw.len());
+
+    for (index, segment) in raw.iter().enumerate() {
+        if let Some(name) = segment.strip_prefix('*') {
+            if index != raw.len() - 1 {
+                bail!("Wildcard segment '{}' must be the last segment of '{}'", segment, pattern);
+            }
+            segments.push(Segment::Wildcard(name.to_string()));
+        } else if let Some(name) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            if name.is_empty() {
+                bail!("Path parameter in '{}' must have a name", pattern);
+            }
+            segments.push(Segment::Param(name.to_string()));
+        } else {
+            segments.push(Segment::Literal(segment.to_string()));
+        }
+    }
+
+    Ok(segments)
+}
+
+fn match_segments(route: &[Segment], path: &[&str]) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+
+    for (index, segment) in route.iter().enumerate() {
+        match segment {
+            Segment::Literal(literal) => {
+                if path.get(index) != Some(&literal.as_str()) {
+                    return None;
+                }
+            }
+            Segment::Param(name) => {
+                let value = path.get(index).filter(|v| is_capturable(v))?;
+                params.insert(name.clone(), percent_decode(value));
+            }
+            Segment::Wildcard(name) => {
+                let rest = path.get(index..).filter(|rest| !rest.is_empty())?;
+                if !rest.iter().all(|v| is_capturable(v)) {
+                    return None;
+                }
+                if !name.is_empty() {
+                    let value = rest.iter().map(|v| percent_decode(v)).collect::<Vec<_>>().join("/");
+                    params.insert(name.clone(), value);
+                }
+                return Some(params);
+            }
+        }
+    }
+
+    (route.len() == path.len()).then_some(params)
+}
+
+/// Captured values end up in file paths, so empty and `..` segments never match.
+fn is_capturable(segment: &str) -> bool {
+    let decoded = percent_decode(segment);
+    !decoded.is_empty() && decoded != "." && decoded != ".." && !decoded.contains(['/', '\\'])
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
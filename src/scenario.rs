@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use serde::Deserialize;
+use crate::config::Endpoint;
+
+/// State every scenario starts in, and returns to when reset
+pub const STARTED: &str = "Started";
+
+/// Ties a variant to a named scenario (a state machine shared by all endpoints).
+///
+/// The variant is only selected while the scenario is in `required_state` (any state when omitted),
+/// and moves the scenario to `new_state` once selected.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ScenarioStep {
+    pub scenario: Option<String>,
+    pub required_state: Option<String>,
+    pub new_state: Option<String>,
+}
+
+/// Current state of each scenario.
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioTracker {
+    states: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl ScenarioStep {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.scenario.is_none() && (self.required_state.is_some() || self.new_state.is_some()) {
+            anyhow::bail!("'required_state' and 'new_state' need a 'scenario'");
+        }
+
+        Ok(())
+    }
+}
+
+impl ScenarioTracker {
+    /// Creates a tracker with every scenario referenced by the endpoints in its initial state.
+    pub fn new<'a>(endpoints: impl IntoIterator<Item = &'a Endpoint>) -> Self {
+        let states = endpoints
+            .into_iter()
+            .flat_map(|endpoint| endpoint.variants.iter())
+            .filter_map(|variant| variant.step.scenario.clone())
+            .map(|name| (name, STARTED.to_string()))
+            .collect();
+
+        ScenarioTracker { states: Arc::new(Mutex::new(states)) }
+    }
+
+    pub fn state(&self, scenario: &str) -> String {
+        self.states
+            .lock()
+            .unwrap()
+            .get(scenario)
+            .cloned()
+            .unwrap_or_else(|| STARTED.to_string())
+    }
+
+    pub fn states(&self) -> HashMap<String, String> {
+        self.states.lock().unwrap().clone()
+    }
+
+    pub fn set_state(&self, scenario: &str, state: &str) {
+        self.states.lock().unwrap().insert(scenario.to_string(), state.to_string());
+    }
+
+    /// Returns whether the scenario is in the state the step requires.
+    pub fn is_active(&self, step: &ScenarioStep) -> bool {
+        match (&step.scenario, &step.required_state) {
+            (Some(scenario), Some(required)) => &self.state(scenario) == required,
+            _ => true,
+        }
+    }
+
+    /// Moves the scenario of a selected step to its new state, if any.
+    pub fn advance(&self, step: &ScenarioStep) {
+        if let (Some(scenario), Some(new_state)) = (&step.scenario, &step.new_state) {
+            tracing::info!("🎬 Scenario {} moves to state {}", scenario, new_state);
+            self.set_state(scenario, new_state);
+        }
+    }
+
+    /// Puts every scenario back in its initial state.
+    pub fn reset(&self) {
+        for state in self.states.lock().unwrap().values_mut() {
+            *state = STARTED.to_string();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use rand::Rng;
+use serde::Deserialize;
+use crate::config::ResponseDefinition;
+
+/// How an endpoint walks through its `responses` list.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceMode {
+    /// One response per call, repeating the last one once the list is exhausted.
+    #[default]
+    Stick,
+    /// One response per call, starting over once the list is exhausted.
+    Cycle,
+    /// A random response per call, picked according to each entry's `weight`.
+    Random,
+}
+
+/// An entry of an endpoint's `responses` list.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SequenceEntry {
+    #[serde(flatten)]
+    pub response: ResponseDefinition,
+    /// Relative weight in `random` mode, 1 when omitted
+    pub weight: Option<u32>,
+}
+
+/// Number of calls served so far by each endpoint with a `responses` list.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceTracker {
+    calls: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl SequenceTracker {
+    /// Picks the response for the next call to the endpoint registered under `key`.
+    pub fn next<'a>(&self, key: &str, entries: &'a [SequenceEntry], mode: SequenceMode) -> Option<&'a SequenceEntry> {
+        self.pick(key, entries, mode, true)
+    }
+
+    /// Picks the response the next call would get, without counting a call (used to answer `HEAD`).
+    pub fn peek<'a>(&self, key: &str, entries: &'a [SequenceEntry], mode: SequenceMode) -> Option<&'a SequenceEntry> {
+        self.pick(key, entries, mode, false)
+    }
+
+    fn pick<'a>(&self, key: &str, entries: &'a [SequenceEntry], mode: SequenceMode, count_call: bool) -> Option<&'a SequenceEntry> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        let mut calls = self.calls.lock().unwrap();
+        let call = calls.entry(key.to_string()).or_insert(0);
+        let index = match mode {
+            SequenceMode::Stick => (*call).min(entries.len() - 1),
+            SequenceMode::Cycle => *call % entries.len(),
+            SequenceMode::Random => weighted_index(entries),
+        };
+        if count_call {
+            *call += 1;
+        }
+
+        entries.get(index)
+    }
+
+    /// Returns the number of calls served so far for every endpoint.
+    pub fn calls(&self) -> HashMap<String, usize> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Starts the sequence of one endpoint, or of every endpoint, from the beginning again.
+    pub fn reset(&self, key: Option<&str>) {
+        let mut calls = self.calls.lock().unwrap();
+        match key {
+            Some(key) => {
+                calls.remove(key);
+            }
+            None => calls.clear(),
+        }
+    }
+}
+
+fn weighted_index(entries: &[SequenceEntry]) -> usize {
+    let weights: Vec<u64> = entries.iter().map(|e| e.weight.unwrap_or(1) as u64).collect();
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+
+    let mut pick = rand::rng().random_range(0..total);
+    for (index, weight) in weights.iter().enumerate() {
+        if pick < *weight {
+            return index;
+        }
+        pick -= weight;
+    }
+
+    entries.len() - 1
+}
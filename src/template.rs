@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderErrorReason};
+use serde_json::{json, Value};
+use uuid::Uuid;
+use crate::request::MockRequest;
+use crate::router::RouteMatch;
+
+/// Renders response files for endpoints with `template: true`.
+///
+/// Templates use Handlebars syntax and can read `method`, `path`, `params` (path parameters),
+/// `query`, `headers` (lower-case names) and `body` (parsed as JSON when possible, raw text otherwise).
+/// The following helpers are available:
+/// - `{{uuid}}`: a random v4 UUID.
+/// - `{{now}}`: the current UTC time in RFC 3339, `{{now "%Y-%m-%d"}}` for a custom format,
+///   or `{{now "epoch"}}` for milliseconds since the Unix epoch.
+/// - `{{counter "name"}}`: a named counter shared by all endpoints, incremented on every use.
+/// - `{{json value}}`: the value serialized as JSON, e.g. `{{json body.items}}`.
+#[derive(Clone)]
+pub struct Templates {
+    registry: Arc<Handlebars<'static>>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        let counters: Arc<Mutex<HashMap<String, u64>>> = Arc::new(Mutex::new(HashMap::new()));
+        let mut registry = Handlebars::new();
+
+        // Responses are JSON, XML or plain text, never HTML
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.register_helper("uuid", Box::new(uuid_helper));
+        registry.register_helper("now", Box::new(now_helper));
+        registry.register_helper("json", Box::new(json_helper));
+        registry.register_helper(
+            "counter",
+            Box::new(move |h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output| -> HelperResult {
+                let name = h.param(0).and_then(|p| p.value().as_str()).unwrap_or("default");
+                let mut counters = counters.lock().unwrap();
+                let value = counters.entry(name.to_string()).or_insert(0);
+                *value += 1;
+                out.write(&value.to_string())?;
+                Ok(())
+            }),
+        );
+
+        Templates { registry: Arc::new(registry) }
+    }
+
+    pub fn render(&self, template: &str, route: &RouteMatch<'_>, request: &MockRequest) -> anyhow::Result<String> {
+        Ok(self.registry.render_template(template, &context(route, request))?)
+    }
+}
+
+fn context(route: &RouteMatch<'_>, request: &MockRequest) -> Value {
+    let headers: HashMap<&str, &str> = request
+        .headers
+        .iter()
+        .filter_map(|(name, value)| value.to_str().ok().map(|value| (name.as_str(), value)))
+        .collect();
+    let body = serde_json::from_slice::<Value>(&request.body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&request.body).into_owned()));
+
+    json!({
+        "method": request.method.as_str(),
+        "path": request.path,
+        "params": route.params,
+        "query": request.query,
+        "headers": headers,
+        "body": body,
+    })
+}
+
+fn uuid_helper(_: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    out.write(&Uuid::new_v4().to_string())?;
+    Ok(())
+}
+
+fn now_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let now = Utc::now();
+    let mut formatted = String::new();
+    match h.param(0).and_then(|p| p.value().as_str()) {
+        Some("epoch") => formatted = now.timestamp_millis().to_string(),
+        Some(format) => write!(formatted, "{}", now.format(format))
+            .map_err(|_| RenderErrorReason::Other(format!("Invalid date format '{}'", format)))?,
+        None => formatted = now.to_rfc3339(),
+    }
+    out.write(&formatted)?;
+    Ok(())
+}
+
+fn json_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let value = h.param(0).ok_or(RenderErrorReason::ParamNotFoundForIndex("json", 0))?;
+    out.write(&serde_json::to_string(value.value()).map_err(RenderErrorReason::SerdeError)?)?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn protected(api_key: &str) -> HashMap<String, Endpoint> {
+    let yaml = format!(
+        "method: [GET]\nbody: ok\nauthentication:\n  api_key:\n{}    keys:\n      - key: sk_test_a\n        name: merchant-a\n      - key: sk_test_b\n        name: merchant-b\n",
+        api_key
+    );
+    HashMap::from([("/payments".to_string(), serde_yaml::from_str::<Endpoint>(&yaml).unwrap())])
+}
+
+#[tokio::test]
+async fn test_api_key_in_default_header() {
+    let api = routes(protected(""), String::from("responses"), new_rate_limit());
+
+    for key in ["sk_test_a", "sk_test_b"] {
+        let res = request().method("GET").path("/payments").header("X-Api-Key", key).reply(&api).await;
+        assert_eq!(res.status(), 200);
+    }
+
+    let res = request().method("GET").path("/payments").header("X-Api-Key", "sk_live_x").reply(&api).await;
+    assert_eq!(res.status(), 401);
+
+    let res = request().method("GET").path("/payments").reply(&api).await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn test_api_key_in_custom_header() {
+    let api = routes(protected("    header: Stripe-Key\n"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/payments").header("stripe-key", "sk_test_b").reply(&api).await;
+    assert_eq!(res.status(), 200);
+
+    let res = request().method("GET").path("/payments").header("X-Api-Key", "sk_test_b").reply(&api).await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn test_api_key_in_query_parameter() {
+    let api = routes(protected("    query: api_key\n"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/payments?api_key=sk_test_a").reply(&api).await;
+    assert_eq!(res.status(), 200);
+
+    let res = request().method("GET").path("/payments?key=sk_test_a").reply(&api).await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn test_api_key_in_cookie() {
+    let api = routes(protected("    cookie: session\n"), String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("GET")
+        .path("/payments")
+        .header("Cookie", "theme=dark; session=sk_test_a")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = request()
+        .method("GET")
+        .path("/payments")
+        .header("Cookie", "session=sk_test_c")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn test_any_of_alternative_credentials() {
+    let yaml = "method: [GET]\nbody: ok\nauthentication:\n  - basic: { user: admin, password: secret }\n  - bearer: { token: valid_token }\n  - api_key: { keys: [{ key: sk_test_a }] }\n";
+    let endpoints = HashMap::from([("/payments".to_string(), serde_yaml::from_str::<Endpoint>(yaml).unwrap())]);
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let status = |name: &'static str, value: &'static str| {
+        let api = api.clone();
+        async move { request().method("GET").path("/payments").header(name, value).reply(&api).await.status() }
+    };
+
+    assert_eq!(status("Authorization", "Basic YWRtaW46c2VjcmV0").await, 200); // admin:secret
+    assert_eq!(status("Authorization", "Bearer valid_token").await, 200);
+    assert_eq!(status("X-Api-Key", "sk_test_a").await, 200);
+    assert_eq!(status("Authorization", "Basic YWRtaW46d3Jvbmc=").await, 401); // admin:wrong
+    assert_eq!(status("Authorization", "Bearer sk_test_a").await, 401);
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use bytes::Bytes;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::{json, Value};
+use warp::http::Response;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn token(claims: Value) -> String {
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(b"top-secret")).unwrap()
+}
+
+async fn get(endpoint: &str, token: &str) -> Response<Bytes> {
+    let endpoints = HashMap::from([("/orders".to_string(), serde_yaml::from_str::<Endpoint>(endpoint).unwrap())]);
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    request()
+        .method("GET")
+        .path("/orders")
+        .header("Authorization", format!("Bearer {}", token))
+        .reply(&api)
+        .await
+}
+
+#[tokio::test]
+async fn test_required_scopes() {
+    let endpoint = "method: [GET]\nbody: ok\nauthentication:\n  bearer:\n    secret: top-secret\n    scopes: [orders:read, orders:write]\n";
+
+    let res = get(endpoint, &token(json!({"scope": "orders:write profile orders:read"}))).await;
+    assert_eq!(res.status(), 200);
+
+    let res = get(endpoint, &token(json!({"scope": "orders:read"}))).await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), "Forbidden\n");
+    assert_eq!(
+        res.headers()["WWW-Authenticate"],
+        "Bearer realm=\"mockserver\", error=\"insufficient_scope\", scope=\"orders:read orders:write\""
+    );
+
+    // An invalid token is still unauthenticated, whatever its scopes
+    let forged = encode(&Header::default(), &json!({"scope": "orders:read orders:write"}), &EncodingKey::from_secret(b"guess")).unwrap();
+    assert_eq!(get(endpoint, &forged).await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_required_role_in_nested_claim() {
+    let endpoint = "method: [GET]\nbody: ok\nauthentication:\n  bearer:\n    secret: top-secret\n    roles: [admin, support]\n    roles_claim: realm_access.roles\n";
+
+    let res = get(endpoint, &token(json!({"realm_access": {"roles": ["viewer", "support"]}}))).await;
+    assert_eq!(res.status(), 200);
+
+    let res = get(endpoint, &token(json!({"realm_access": {"roles": ["viewer"]}}))).await;
+    assert_eq!(res.status(), 403);
+
+    let res = get(endpoint, &token(json!({"roles": ["admin"]}))).await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn test_claims_matching_patterns() {
+    let endpoint = r#"
+method: [GET]
+body: ok
+authentication:
+  bearer:
+    secret: top-secret
+    claims_match:
+      email: '@acme\.com$'
+      tenant_id: '^[0-9]+$'
+      groups: '^billing-'
+"#;
+
+    let res = get(endpoint, &token(json!({"email": "ada@acme.com", "tenant_id": 42, "groups": ["staff", "billing-eu"]}))).await;
+    assert_eq!(res.status(), 200);
+
+    let cases = [
+        json!({"email": "ada@evil.com", "tenant_id": 42, "groups": ["billing-eu"]}),
+        json!({"email": "ada@acme.com", "tenant_id": "t-42", "groups": ["billing-eu"]}),
+        json!({"email": "ada@acme.com", "tenant_id": 42, "groups": ["staff"]}),
+        json!({"tenant_id": 42, "groups": ["billing-eu"]}),
+    ];
+    for claims in cases {
+        assert_eq!(get(endpoint, &token(claims.clone())).await.status(), 403, "{} should be forbidden", claims);
+    }
+}
+
+#[tokio::test]
+async fn test_custom_forbidden_response() {
+    let endpoint = r#"
+method: [GET]
+body: ok
+authentication:
+  bearer:
+    secret: top-secret
+    roles: [admin]
+forbidden:
+  json: { error: permission_denied }
+  headers:
+    X-Required-Role: admin
+"#;
+
+    let res = get(endpoint, &token(json!({"roles": ["viewer"]}))).await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), r#"{"error":"permission_denied"}"#);
+    assert_eq!(res.headers()["X-Required-Role"], "admin");
+    assert_eq!(res.headers()["WWW-Authenticate"], "Bearer realm=\"mockserver\", error=\"insufficient_scope\"");
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn slow_endpoint(yaml: &str) -> HashMap<String, Endpoint> {
+    let endpoint = format!("method: [GET]\nbody: ok\ndelay: 300\nmax_concurrent: 1\n{}", yaml);
+    HashMap::from([("/pool".to_string(), serde_yaml::from_str::<Endpoint>(&endpoint).unwrap())])
+}
+
+#[tokio::test]
+async fn test_saturated_endpoint_answers_503_at_once() {
+    let api = routes(slow_endpoint(""), "responses".to_string(), new_rate_limit());
+
+    let first = request().method("GET").path("/pool").reply(&api);
+    let second = async {
+        sleep(Duration::from_millis(50)).await;
+        let started = Instant::now();
+        let res = request().method("GET").path("/pool").reply(&api).await;
+        (res, started.elapsed())
+    };
+    let (first, (second, waited)) = tokio::join!(first, second);
+
+    assert_eq!(first.status(), 200);
+    assert_eq!(second.status(), 503);
+    assert!(waited < Duration::from_millis(200), "waited {:?}", waited);
+
+    // The slot is free again once the first response is sent
+    let res = request().method("GET").path("/pool").reply(&api).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_queued_request_waits_for_a_slot() {
+    let api = routes(slow_endpoint("queue_timeout_ms: 1000\n"), "responses".to_string(), new_rate_limit());
+
+    let started = Instant::now();
+    let first = request().method("GET").path("/pool").reply(&api);
+    let second = async {
+        sleep(Duration::from_millis(50)).await;
+        request().method("GET").path("/pool").reply(&api).await
+    };
+    let (first, second) = tokio::join!(first, second);
+
+    assert_eq!(first.status(), 200);
+    assert_eq!(second.status(), 200);
+    // The second request only starts its own delay once the first one is done
+    assert!(started.elapsed() >= Duration::from_millis(600), "took {:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_queued_request_times_out() {
+    let api = routes(slow_endpoint("queue_timeout_ms: 100\n"), "responses".to_string(), new_rate_limit());
+
+    let first = request().method("GET").path("/pool").reply(&api);
+    let second = async {
+        sleep(Duration::from_millis(50)).await;
+        let started = Instant::now();
+        let res = request().method("GET").path("/pool").reply(&api).await;
+        (res, started.elapsed())
+    };
+    let (first, (second, waited)) = tokio::join!(first, second);
+
+    assert_eq!(first.status(), 200);
+    assert_eq!(second.status(), 503);
+    assert!(waited >= Duration::from_millis(100) && waited < Duration::from_millis(250), "waited {:?}", waited);
+}
+
+#[tokio::test]
+async fn test_slots_are_per_endpoint() {
+    let mut endpoints = slow_endpoint("");
+    endpoints.insert("/other".to_string(), serde_yaml::from_str("method: [GET]\nbody: ok\nmax_concurrent: 1\n").unwrap());
+    let api = routes(endpoints, "responses".to_string(), new_rate_limit());
+
+    let first = request().method("GET").path("/pool").reply(&api);
+    let other = async {
+        sleep(Duration::from_millis(50)).await;
+        request().method("GET").path("/other").reply(&api).await
+    };
+    let (first, other) = tokio::join!(first, other);
+
+    assert_eq!(first.status(), 200);
+    assert_eq!(other.status(), 200);
+}
@@ -0,0 +1,278 @@
+use std::fs;
+use mockserver::config::{load_config, Authentication};
+
+fn load(name: &str, yaml: &str) -> anyhow::Result<mockserver::config::Config> {
+    let path = std::env::temp_dir().join(format!("mockserver_{}.yaml", name));
+    fs::write(&path, yaml).unwrap();
+    load_config(path.to_str().unwrap())
+}
+
+#[test]
+fn test_load_config_with_per_method_blocks() {
+    let config = load(
+        "per_method",
+        r#"
+/api/order:
+  method:
+    - GET
+  file: orders.json
+  POST:
+    file: created.json
+    status: 201
+"#,
+    )
+    .unwrap();
+
+    let endpoint = &config.endpoints["/api/order"];
+    assert_eq!(endpoint.methods["POST"].file.as_deref(), Some("created.json"));
+    assert_eq!(endpoint.methods["POST"].status_code, Some(201));
+    assert!(endpoint.allows_method("POST"));
+    assert!(!endpoint.allows_method("PUT"));
+}
+
+#[test]
+fn test_load_config_with_only_method_blocks() {
+    let config = load(
+        "only_method_blocks",
+        r#"
+/api/order:
+  GET:
+    file: orders.json
+  POST:
+    body: created
+    status_code: 201
+"#,
+    )
+    .unwrap();
+
+    let endpoint = &config.endpoints["/api/order"];
+    assert!(endpoint.method.is_empty());
+    assert!(endpoint.allows_method("GET"));
+    assert!(endpoint.allows_method("POST"));
+    assert!(!endpoint.allows_method("DELETE"));
+
+    let err = load("no_method", "/api/order:\n  file: orders.json\n").unwrap_err();
+    assert!(format!("{:#}", err).contains("No method is served"));
+}
+
+#[test]
+fn test_load_config_rejects_unknown_method_block() {
+    let err = load(
+        "unknown_method",
+        r#"
+/api/order:
+  method:
+    - GET
+  file: orders.json
+  FETCH:
+    file: created.json
+"#,
+    )
+    .unwrap_err();
+
+    assert!(format!("{:#}", err).contains("/api/order"));
+}
+
+#[test]
+fn test_load_config_rejects_mistyped_endpoint_fields() {
+    for (name, field) in [("mistyped_status", "stauts_code: 201"), ("mistyped_delay", "dealy: 100")] {
+        let yaml = format!("/api/order:\n  method: [GET]\n  file: orders.json\n  {}\n", field);
+        let message = format!("{:#}", load(name, &yaml).unwrap_err());
+        let key = field.split(':').next().unwrap();
+        assert!(message.contains(&format!("unknown field or HTTP method '{}'", key)), "{}", message);
+    }
+}
+
+#[test]
+fn test_load_config_rejects_unknown_top_level_keys() {
+    let err = load(
+        "unknown_section",
+        r#"
+rate_limits:
+  requests: 10
+  window_ms: 1000
+
+/api/order:
+  method:
+    - GET
+  file: orders.json
+"#,
+    )
+    .unwrap_err();
+
+    let message = format!("{:#}", err);
+    assert!(message.contains("Unknown top-level key 'rate_limits'"), "{}", message);
+}
+
+#[test]
+fn test_load_config_with_inline_bodies() {
+    let config = load(
+        "inline_bodies",
+        r#"
+/api/health:
+  method:
+    - GET
+  body: '{"ok":true}'
+
+/api/user:
+  method:
+    - GET
+  json:
+    id: 1
+    roles: [admin]
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.endpoints["/api/health"].body.as_deref(), Some("{\"ok\":true}"));
+    assert_eq!(config.endpoints["/api/user"].json.as_ref().unwrap()["roles"][0], "admin");
+}
+
+#[test]
+fn test_load_config_rejects_several_body_sources() {
+    let err = load(
+        "several_sources",
+        r#"
+/api/health:
+  method:
+    - GET
+  file: health.json
+  body: '{"ok":true}'
+"#,
+    )
+    .unwrap_err();
+
+    let message = format!("{:#}", err);
+    assert!(message.contains("/api/health"));
+    assert!(message.contains("Only one of"));
+}
+
+#[test]
+fn test_load_config_requires_a_body_source() {
+    let err = load(
+        "missing_source",
+        r#"
+/api/health:
+  method:
+    - GET
+  POST:
+    status: 204
+"#,
+    )
+    .unwrap_err();
+
+    assert!(format!("{:#}", err).contains("POST"));
+
+    let err = load(
+        "missing_default_source",
+        r#"
+/api/health:
+  method:
+    - GET
+  POST:
+    body: created
+"#,
+    )
+    .unwrap_err();
+
+    assert!(format!("{:#}", err).contains("to serve GET"));
+}
+
+#[test]
+fn test_load_config_rejects_unknown_variant_keys() {
+    let err = load(
+        "unknown_variant_key",
+        r#"
+/api/users:
+  method:
+    - GET
+  file: users.json
+  variants:
+    - request_header:
+        X-Tenant: acme
+      response:
+        file: users_acme.json
+"#,
+    )
+    .unwrap_err();
+
+    assert!(format!("{:#}", err).contains("unknown field `request_header`"));
+}
+
+#[test]
+fn test_load_config_with_alternative_credentials() {
+    let config = load(
+        "alternative_credentials",
+        r#"
+/api/admin:
+  method: [GET]
+  body: ok
+  authentication:
+    - basic: { user: admin, password: '1234' }
+    - api_key:
+        keys:
+          - key: sk_test_1
+"#,
+    )
+    .unwrap();
+
+    match config.endpoints["/api/admin"].authentication.as_ref().unwrap() {
+        Authentication::AnyOf(alternatives) => {
+            assert!(matches!(&alternatives[0], Authentication::Basic(basic) if basic.password == "1234"));
+            assert!(matches!(&alternatives[1], Authentication::ApiKey(_)));
+        }
+        other => panic!("Expected alternatives, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_load_config_rejects_invalid_authentication() {
+    let cases = [
+        ("numeric_password", "basic: { user: admin, password: 1234 }", "expected a string"),
+        ("unknown_scheme", "baisc: { user: admin, password: secret }", "unknown authentication scheme \"baisc\""),
+        ("unknown_field", "basic: { user: admin, pasword: secret }", "unknown field `pasword`"),
+        ("unknown_bearer_field", "bearer: { secret: s, scope: [admin] }", "unknown field `scope`"),
+        ("several_schemes", "{ basic: { user: a, password: b }, api_key: { keys: [] } }", "a list of them"),
+        ("bearer_without_token", "bearer: { claims: { sub: ada } }", "needs a 'token' or a key"),
+        ("two_keys", "bearer: { secret: s, jwks: tests/keys/jwks.json }", "Only one of 'secret'"),
+        ("no_api_keys", "api_key: { header: X-Key, keys: [] }", "at least one of 'keys'"),
+        ("oauth_without_issuer", "bearer: { oauth: true }", "no 'oauth' issuer"),
+        ("invalid_claim_pattern", "bearer: { secret: s, claims_match: { email: '(' } }", "regex parse error"),
+        ("missing_public_key", "bearer: { public_key: /nonexistent.pem }", "Cannot read /nonexistent.pem"),
+        ("missing_jwks", "bearer: { jwks: keys/jwks.json }", "Cannot read keys/jwks.json"),
+        ("invalid_public_key", "bearer: { public_key: tests/keys/jwks.json }", "not an RSA or EC public key"),
+        ("invalid_jwks", "bearer: { jwks: tests/keys/rsa_public.pem }", "not a JWKS document"),
+    ];
+
+    for (name, authentication, expected) in cases {
+        let yaml = format!("/api/admin:\n  method: [GET]\n  body: ok\n  authentication:\n    {}\n", authentication);
+        let message = format!("{:#}", load(name, &yaml).unwrap_err());
+        assert!(message.contains("/api/admin"), "{}: {}", name, message);
+        assert!(message.contains(expected), "{}: {}", name, message);
+    }
+}
+
+#[test]
+fn test_load_config_rejects_invalid_concurrency_limits() {
+    let cases = [
+        ("zero_concurrency", "max_concurrent: 0\n", "'max_concurrent' must be positive"),
+        ("queue_without_limit", "queue_timeout_ms: 500\n", "only used with 'max_concurrent'"),
+    ];
+
+    for (name, settings, expected) in cases {
+        let yaml = format!("/api/pool:\n  method: [GET]\n  body: ok\n  {}", settings);
+        let message = format!("{:#}", load(name, &yaml).unwrap_err());
+        assert!(message.contains("/api/pool"), "{}: {}", name, message);
+        assert!(message.contains(expected), "{}: {}", name, message);
+    }
+}
+
+#[test]
+fn test_readme_example_config_loads() {
+    let readme = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md")).unwrap();
+    let start = readme.find("```yaml\n").expect("README has a yaml example") + "```yaml\n".len();
+    let end = start + readme[start..].find("```").unwrap();
+
+    let config = load("readme", &readme[start..end]).unwrap_or_else(|err| panic!("{:#}", err));
+    assert!(config.endpoints.contains_key("/api/user"));
+}
@@ -0,0 +1,235 @@
+use warp::test::request;
+use mockserver::config::{Config, Endpoint};
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+use std::collections::HashMap;
+
+fn config(yaml: &str) -> Config {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[tokio::test]
+async fn test_any_origin_allowed_by_default() {
+    let endpoints = HashMap::from([(
+        "/api/open".to_string(),
+        serde_yaml::from_str::<Endpoint>("method: [GET]\nbody: ok\n").unwrap(),
+    )]);
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("GET")
+        .path("/api/open")
+        .header("Origin", "http://localhost:3000")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "*");
+}
+
+#[tokio::test]
+async fn test_configured_origins_and_credentials() {
+    let api = routes(
+        config(
+            r#"
+cors:
+  origins:
+    - https://app.example.com
+    - https://*.preview.example.com
+  credentials: true
+  exposed_headers: [X-Request-Id]
+/api/me:
+  method: [GET]
+  json:
+    name: Ada
+"#,
+        ),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let res = request()
+        .method("GET")
+        .path("/api/me")
+        .header("Origin", "https://pr-42.preview.example.com")
+        .reply(&api)
+        .await;
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "https://pr-42.preview.example.com");
+    assert_eq!(res.headers()["Access-Control-Allow-Credentials"], "true");
+    assert_eq!(res.headers()["Access-Control-Expose-Headers"], "X-Request-Id");
+    assert_eq!(res.headers()["Vary"], "Origin");
+
+    let res = request()
+        .method("GET")
+        .path("/api/me")
+        .header("Origin", "https://evil.example.org")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert!(!res.headers().contains_key("Access-Control-Allow-Origin"));
+}
+
+#[tokio::test]
+async fn test_preflight_checks_methods_and_headers() {
+    let api = routes(
+        config(
+            r#"
+cors:
+  methods: [GET, POST]
+  allowed_headers: [Content-Type, X-Tenant]
+  max_age: 600
+/api/orders:
+  method: [GET, POST, DELETE]
+  body: '[]'
+"#,
+        ),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let res = request()
+        .method("OPTIONS")
+        .path("/api/orders")
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type, x-tenant")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Access-Control-Allow-Methods"], "GET, POST");
+    assert_eq!(res.headers()["Access-Control-Allow-Headers"], "content-type, x-tenant");
+    assert_eq!(res.headers()["Access-Control-Max-Age"], "600");
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "*");
+
+    let res = request()
+        .method("OPTIONS")
+        .path("/api/orders")
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "DELETE")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 403);
+
+    let res = request()
+        .method("OPTIONS")
+        .path("/api/orders")
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "GET")
+        .header("Access-Control-Request-Headers", "X-Debug")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn test_endpoint_overrides_and_disables_cors() {
+    let api = routes(
+        config(
+            r#"
+cors:
+  origins: [https://app.example.com]
+/api/public:
+  method: [GET]
+  body: ok
+  cors:
+    origins: ['*']
+/api/internal:
+  method: [GET]
+  body: ok
+  cors:
+    enabled: false
+"#,
+        ),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let res = request()
+        .method("GET")
+        .path("/api/public")
+        .header("Origin", "https://other.example.com")
+        .reply(&api)
+        .await;
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "*");
+
+    let res = request()
+        .method("GET")
+        .path("/api/internal")
+        .header("Origin", "https://app.example.com")
+        .reply(&api)
+        .await;
+    assert!(!res.headers().contains_key("Access-Control-Allow-Origin"));
+
+    // Without CORS, a preflight is a plain OPTIONS request
+    let res = request()
+        .method("OPTIONS")
+        .path("/api/internal")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "GET")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 204);
+    assert!(!res.headers().contains_key("Access-Control-Allow-Methods"));
+}
+
+#[tokio::test]
+async fn test_rejections_carry_cors_headers() {
+    let api = routes(
+        config(
+            r#"
+/api/limited:
+  method: [GET]
+  body: ok
+  rate_limit:
+    requests: 1
+    window_ms: 60000
+"#,
+        ),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let send = || request().method("GET").path("/api/limited").header("Origin", "http://localhost:3000");
+    assert_eq!(send().reply(&api).await.status(), 200);
+
+    let res = send().reply(&api).await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "*");
+}
+
+#[tokio::test]
+async fn test_admin_endpoints_follow_the_cors_section() {
+    let api = routes(
+        config("cors:\n  origins: [https://app.example.com]\n  methods: [GET, PUT]\n"),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let res = request()
+        .method("OPTIONS")
+        .path("/__admin/scenarios/cart/state")
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "PUT")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Access-Control-Allow-Methods"], "GET, PUT");
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "https://app.example.com");
+
+    let res = request()
+        .method("GET")
+        .path("/__admin/sequences")
+        .header("Origin", "https://app.example.com")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Access-Control-Allow-Origin"], "https://app.example.com");
+
+    let res = request()
+        .method("GET")
+        .path("/__admin/sequences")
+        .header("Origin", "https://evil.example.org")
+        .reply(&api)
+        .await;
+    assert!(!res.headers().contains_key("Access-Control-Allow-Origin"));
+}
@@ -13,11 +13,12 @@ async fn test_get_existing_file() {
         "/test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: None,
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -37,11 +38,12 @@ async fn test_get_non_existent_file() {
         "/missing".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "missing.json".to_string(),
+            file: Some("missing.json".to_string()),
             status_code: None,
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -58,11 +60,12 @@ async fn test_post_create_file() {
         "/create".to_string(),
         Endpoint {
             method: vec!["POST".to_string()],
-            file: "create.json".to_string(),
+            file: Some("create.json".to_string()),
             status_code: Some(201),
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -86,11 +89,12 @@ async fn test_delete_existing_file() {
         "/delete".to_string(),
         Endpoint {
             method: vec!["DELETE".to_string()],
-            file: "delete.json".to_string(),
+            file: Some("delete.json".to_string()),
             status_code: Some(205),
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -110,11 +114,12 @@ async fn test_method_not_allowed() {
         "/forbidden".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "forbidden.json".to_string(),
+            file: Some("forbidden.json".to_string()),
             status_code: None,
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -135,11 +140,12 @@ async fn test_get_existing_file_with_custom_status() {
         "/test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(201),
             authentication: None,
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -159,13 +165,14 @@ async fn test_unauthorized_access_basic() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str("basic: { user: 'admin', password: 'secret' }").unwrap(),
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -181,7 +188,7 @@ async fn test_valid_basic_auth() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str(
@@ -195,6 +202,7 @@ async fn test_valid_basic_auth() {
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -219,7 +227,7 @@ async fn test_valid_bearer_token() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str(
@@ -232,9 +240,12 @@ async fn test_valid_bearer_token() {
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
+    fs::write("responses/protected.json", "{\"message\": \"ok\"}").unwrap();
+
     let api =routes(endpoints, String::from("responses"), new_rate_limit());
 
     // Valid Bearer Token (should return status 200)
@@ -255,7 +266,7 @@ async fn test_invalid_bearer_token() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str(
@@ -268,6 +279,7 @@ async fn test_invalid_bearer_token() {
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -291,7 +303,7 @@ async fn test_edge_case_missing_claims_in_bearer_token() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str(
@@ -306,6 +318,7 @@ async fn test_edge_case_missing_claims_in_bearer_token() {
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -329,7 +342,7 @@ async fn test_edge_case_invalid_claims_in_bearer_token() {
         "/protected".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: Some(
                 serde_yaml::from_str(
@@ -344,6 +357,7 @@ async fn test_edge_case_invalid_claims_in_bearer_token() {
             ),
             delay: None,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -367,14 +381,17 @@ async fn test_response_with_delay() {
         "/test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: Some(500), // 500ms delay
             rate_limit: None,
+            ..Default::default()
         },
     );
 
+    fs::write("responses/protected.json", "{\"message\": \"ok\"}").unwrap();
+
     let start_time = Instant::now();
     let api =routes(endpoints, String::from("responses"), new_rate_limit());
 
@@ -396,14 +413,17 @@ async fn test_response_with_zero_delay() {
         "/test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "protected.json".to_string(),
+            file: Some("protected.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: Some(0), // Edge case: 0 delay
             rate_limit: None,
+            ..Default::default()
         },
     );
 
+    fs::write("responses/protected.json", "{\"message\": \"ok\"}").unwrap();
+
     let start_time = Instant::now();
 
     let api =routes(endpoints, String::from("responses"), new_rate_limit());
@@ -416,4 +436,126 @@ async fn test_response_with_zero_delay() {
     let elapsed = start_time.elapsed();
     assert!(elapsed.as_millis() < 50, "Expected minimal delay for 0ms setting");
     assert_eq!(res.status(), 200);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_per_method_responses() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/orders".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - DELETE
+            file: orders_list.json
+            GET:
+              file: orders_list.json
+              headers:
+                Cache-Control: no-store
+            POST:
+              file: orders_created.json
+              status: 201
+              headers:
+                Location: /orders/1
+            "#,
+        )
+        .unwrap(),
+    );
+
+    fs::write("responses/orders_list.json", "[{\"id\": 1}]").unwrap();
+    fs::write("responses/orders_created.json", "{\"id\": 1}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/orders").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "[{\"id\": 1}]");
+    assert_eq!(res.headers()["Cache-Control"], "no-store");
+
+    let res = request()
+        .method("POST")
+        .path("/orders")
+        .body("{\"item\": \"Laptop\"}")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.body(), "{\"id\": 1}");
+    assert_eq!(res.headers()["Location"], "/orders/1");
+
+    // The POST must not clobber the GET fixture
+    let res = request().method("GET").path("/orders").reply(&api).await;
+    assert_eq!(res.body(), "[{\"id\": 1}]");
+
+    let res = request().method("PUT").path("/orders").reply(&api).await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(res.headers()["Allow"], "GET, POST, DELETE, HEAD, OPTIONS");
+}
+
+#[tokio::test]
+async fn test_head_returns_get_headers_without_body() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/head".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+            file: head.json
+            status_code: 203
+            headers:
+              ETag: '"v1"'
+            "#,
+        )
+        .unwrap(),
+    );
+    fs::write("responses/head.json", "{\"id\": 1}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("HEAD").path("/head").reply(&api).await;
+
+    assert_eq!(res.status(), 203);
+    assert_eq!(res.headers()["Content-Type"], "application/json");
+    assert_eq!(res.headers()["Content-Length"], "9");
+    assert_eq!(res.headers()["ETag"], "\"v1\"");
+    assert!(res.body().is_empty());
+}
+
+#[tokio::test]
+async fn test_options_lists_allowed_methods() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/discover".to_string(),
+        Endpoint {
+            method: vec!["GET".to_string(), "DELETE".to_string()],
+            file: Some("discover.json".to_string()),
+            ..Default::default()
+        },
+    );
+    endpoints.insert(
+        "/custom".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+            file: discover.json
+            OPTIONS:
+              json:
+                version: 2
+              headers:
+                Allow: GET, OPTIONS
+            "#,
+        )
+        .unwrap(),
+    );
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request().method("OPTIONS").path("/discover").reply(&api).await;
+    assert_eq!(res.status(), 204);
+    assert_eq!(res.headers()["Allow"], "GET, DELETE, HEAD, OPTIONS");
+    assert!(res.body().is_empty());
+
+    let res = request().method("OPTIONS").path("/custom").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Allow"], "GET, OPTIONS");
+    assert_eq!(res.body(), "{\"version\":2}");
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::fs;
+use jsonwebtoken::{encode, get_current_timestamp, Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn protected(bearer: &str) -> HashMap<String, Endpoint> {
+    let yaml = format!("method: [GET]\nbody: ok\nauthentication:\n  bearer:\n{}", bearer);
+    HashMap::from([("/protected".to_string(), serde_yaml::from_str::<Endpoint>(&yaml).unwrap())])
+}
+
+fn sign(alg: Algorithm, kid: Option<&str>, claims: Value, key: &EncodingKey) -> String {
+    let mut header = Header::new(alg);
+    header.kid = kid.map(str::to_string);
+    encode(&header, &claims, key).unwrap()
+}
+
+fn hs256(claims: Value) -> String {
+    sign(Algorithm::HS256, None, claims, &EncodingKey::from_secret(b"top-secret"))
+}
+
+async fn status(endpoints: &HashMap<String, Endpoint>, token: &str) -> u16 {
+    let api = routes(endpoints.clone(), String::from("responses"), new_rate_limit());
+    request()
+        .method("GET")
+        .path("/protected")
+        .header("Authorization", format!("Bearer {}", token))
+        .reply(&api)
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn test_hs256_token_with_registered_claims() {
+    let endpoints = protected(
+        "    secret: top-secret\n    issuer: https://auth.example.com\n    audience: orders-api\n    claims:\n      role: admin\n",
+    );
+    let now = get_current_timestamp();
+    let valid = json!({"iss": "https://auth.example.com", "aud": "orders-api", "exp": now + 60, "role": "admin"});
+
+    assert_eq!(status(&endpoints, &hs256(valid.clone())).await, 200);
+
+    let wrong_secret = sign(Algorithm::HS256, None, valid.clone(), &EncodingKey::from_secret(b"guess"));
+    assert_eq!(status(&endpoints, &wrong_secret).await, 401);
+
+    let cases = [
+        ("exp", json!(now - 120)),
+        ("nbf", json!(now + 120)),
+        ("aud", json!("billing-api")),
+        ("iss", json!("https://evil.example.com")),
+    ];
+    for (claim, value) in cases {
+        let mut claims = valid.clone();
+        claims[claim] = value;
+        assert_eq!(status(&endpoints, &hs256(claims)).await, 401, "{} should be rejected", claim);
+    }
+
+    // A genuine token without the configured claims is forbidden rather than unauthenticated
+    let mut viewer = valid.clone();
+    viewer["role"] = json!("viewer");
+    assert_eq!(status(&endpoints, &hs256(viewer)).await, 403);
+
+    let mut missing_issuer = valid.clone();
+    missing_issuer.as_object_mut().unwrap().remove("iss");
+    assert_eq!(status(&endpoints, &hs256(missing_issuer)).await, 401);
+}
+
+#[tokio::test]
+async fn test_rs256_token_with_pem_public_key() {
+    let endpoints = protected("    public_key: tests/keys/rsa_public.pem\n");
+    let private_key = EncodingKey::from_rsa_pem(&fs::read("tests/keys/rsa_private.pem").unwrap()).unwrap();
+    let token = sign(Algorithm::RS256, None, json!({"sub": "ada"}), &private_key);
+
+    assert_eq!(status(&endpoints, &token).await, 200);
+
+    // The public key must not be usable as an HMAC secret
+    let forged = sign(
+        Algorithm::HS256,
+        None,
+        json!({"sub": "ada"}),
+        &EncodingKey::from_secret(&fs::read("tests/keys/rsa_public.pem").unwrap()),
+    );
+    assert_eq!(status(&endpoints, &forged).await, 401);
+}
+
+#[tokio::test]
+async fn test_es256_token_with_jwks_key() {
+    let endpoints = protected("    jwks: tests/keys/jwks.json\n    claims:\n      sub: grace\n");
+    let private_key = EncodingKey::from_ec_pem(&fs::read("tests/keys/ec_private.pem").unwrap()).unwrap();
+
+    let token = sign(Algorithm::ES256, Some("ec-key"), json!({"sub": "grace"}), &private_key);
+    assert_eq!(status(&endpoints, &token).await, 200);
+
+    let unknown_kid = sign(Algorithm::ES256, Some("rotated"), json!({"sub": "grace"}), &private_key);
+    assert_eq!(status(&endpoints, &unknown_kid).await, 401);
+
+    let wrong_key = sign(Algorithm::ES256, Some("rsa-key"), json!({"sub": "grace"}), &private_key);
+    assert_eq!(status(&endpoints, &wrong_key).await, 401);
+}
+
+#[tokio::test]
+async fn test_static_jwt_token_claims_are_compared() {
+    let token = hs256(json!({"sub": "SUB", "iat": 1516238972}));
+    let endpoints = protected(&format!("    token: '{}'\n    claims:\n      sub: SUB\n      iat: 1516238972\n", token));
+    assert_eq!(status(&endpoints, &token).await, 200);
+
+    let endpoints = protected(&format!("    token: '{}'\n    claims:\n      sub: OTHER\n", token));
+    assert_eq!(status(&endpoints, &token).await, 401);
+}
+
+#[tokio::test]
+async fn test_garbage_token_is_rejected_when_verifying() {
+    let endpoints = protected("    secret: top-secret\n");
+    assert_eq!(status(&endpoints, "not.a.jwt").await, 401);
+    assert_eq!(status(&endpoints, "valid_token").await, 401);
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fs;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn variant_endpoint() -> Endpoint {
+    serde_yaml::from_str(
+        r#"
+        method:
+          - GET
+        file: variants_default.json
+        variants:
+          - query:
+              status: active
+            response:
+              file: variants_active.json
+          - headers:
+              Accept: application/xml
+            response:
+              file: variants_xml.json
+              status_code: 203
+          - query:
+              status: active
+            headers:
+              X-Tenant: acme
+            response:
+              file: variants_unreachable.json
+          - headers:
+              X-Tenant: acme
+            response:
+              file: variants_acme.json
+              headers:
+                X-Served-By: acme
+        "#,
+    )
+    .unwrap()
+}
+
+fn write_fixtures() {
+    fs::write("responses/variants_default.json", "default").unwrap();
+    fs::write("responses/variants_active.json", "active").unwrap();
+    fs::write("responses/variants_xml.json", "xml").unwrap();
+    fs::write("responses/variants_acme.json", "acme").unwrap();
+}
+
+#[tokio::test]
+async fn test_variant_selected_by_query_parameter() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/variants/query".to_string(), variant_endpoint());
+    write_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/variants/query?status=active").reply(&api).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "active");
+
+    let res = request().method("GET").path("/variants/query?status=inactive").reply(&api).await;
+    assert_eq!(res.body(), "default");
+}
+
+#[tokio::test]
+async fn test_variant_selected_by_header_with_custom_status() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/variants/header".to_string(), variant_endpoint());
+    write_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("GET")
+        .path("/variants/header")
+        .header("accept", "application/xml")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 203);
+    assert_eq!(res.body(), "xml");
+}
+
+#[tokio::test]
+async fn test_variant_response_headers_are_sent() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/variants/tenant".to_string(), variant_endpoint());
+    write_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("GET")
+        .path("/variants/tenant")
+        .header("X-Tenant", "acme")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.body(), "acme");
+    assert_eq!(res.headers()["X-Served-By"], "acme");
+    // Header conditions are matched against the request, never sent back
+    assert!(!res.headers().contains_key("X-Tenant"));
+}
+
+#[tokio::test]
+async fn test_first_matching_variant_wins() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/variants/order".to_string(), variant_endpoint());
+    write_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("GET")
+        .path("/variants/order?status=active")
+        .header("X-Tenant", "acme")
+        .reply(&api)
+        .await;
+    assert_eq!(res.body(), "active");
+
+    let res = request()
+        .method("GET")
+        .path("/variants/order")
+        .header("X-Tenant", "acme")
+        .reply(&api)
+        .await;
+    assert_eq!(res.body(), "acme");
+}
+
+#[tokio::test]
+async fn test_falls_back_to_endpoint_file_without_matching_variant() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/variants/fallback".to_string(), variant_endpoint());
+    write_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("GET")
+        .path("/variants/fallback")
+        .header("X-Tenant", "other")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "default");
+}
+
+fn order_endpoint() -> Endpoint {
+    serde_yaml::from_str(
+        r#"
+        method:
+          - POST
+        file: body_orders.json
+        variants:
+          - body:
+              json_path: "$.items[?@.sku == 'INVALID']"
+            response:
+              file: body_invalid_sku.json
+              status_code: 422
+          - body:
+              equals_json:
+                ping: true
+            response:
+              file: body_pong.json
+              status_code: 200
+          - body:
+              contains_json:
+                customer:
+                  tier: gold
+                items:
+                  - sku: GIFT
+            response:
+              file: body_gold.json
+          - body:
+              matches: "^<order"
+            response:
+              file: body_xml.json
+              status_code: 415
+        "#,
+    )
+    .unwrap()
+}
+
+fn write_body_fixtures() {
+    fs::write("responses/body_invalid_sku.json", "{\"error\": \"invalid sku\"}").unwrap();
+    fs::write("responses/body_pong.json", "pong").unwrap();
+    fs::write("responses/body_gold.json", "gold").unwrap();
+    fs::write("responses/body_xml.json", "xml not supported").unwrap();
+}
+
+#[tokio::test]
+async fn test_body_json_path_selects_variant() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/body/json_path".to_string(), order_endpoint());
+    write_body_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("POST")
+        .path("/body/json_path")
+        .body(r#"{"items": [{"sku": "OK"}, {"sku": "INVALID"}]}"#)
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.body(), "{\"error\": \"invalid sku\"}");
+}
+
+#[tokio::test]
+async fn test_body_exact_json_selects_variant() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/body/equals".to_string(), order_endpoint());
+    write_body_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("POST")
+        .path("/body/equals")
+        .body(r#"{ "ping": true }"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "pong");
+
+    // Extra fields break exact equality, so the default behaviour applies
+    let res = request()
+        .method("POST")
+        .path("/body/equals")
+        .body(r#"{"ping": true, "extra": 1}"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.body(), "Created\n");
+}
+
+#[tokio::test]
+async fn test_body_partial_json_selects_variant() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/body/contains".to_string(), order_endpoint());
+    write_body_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("POST")
+        .path("/body/contains")
+        .body(r#"{"customer": {"id": 7, "tier": "gold"}, "items": [{"sku": "A"}, {"sku": "GIFT", "qty": 1}]}"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.body(), "gold");
+
+    let res = request()
+        .method("POST")
+        .path("/body/contains")
+        .body(r#"{"customer": {"id": 7, "tier": "silver"}, "items": [{"sku": "GIFT"}]}"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.body(), "Created\n");
+}
+
+#[tokio::test]
+async fn test_body_regex_selects_variant() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/body/regex".to_string(), order_endpoint());
+    write_body_fixtures();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("POST")
+        .path("/body/regex")
+        .body("<order><sku>A</sku></order>")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 415);
+    assert_eq!(res.body(), "xml not supported");
+}
+
+#[test]
+fn test_invalid_body_regex_is_rejected_when_loading() {
+    let endpoint = serde_yaml::from_str::<Endpoint>(
+        r#"
+        method: [POST]
+        file: x.json
+        variants:
+          - body:
+              matches: "(unclosed"
+            response:
+              file: y.json
+        "#,
+    );
+
+    assert!(endpoint.is_err());
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs;
+use serde_json::{json, Value};
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::patch::{apply_patch, JSON_PATCH, MERGE_PATCH};
+use mockserver::rate_limit::new_rate_limit;
+
+fn patchable_endpoint(key: &str, file: &str) -> HashMap<String, Endpoint> {
+    HashMap::from([(
+        key.to_string(),
+        Endpoint {
+            method: vec!["GET".to_string(), "PATCH".to_string()],
+            file: Some(file.to_string()),
+            ..Default::default()
+        },
+    )])
+}
+
+#[test]
+fn test_merge_patch_replaces_and_removes_fields() {
+    let mut document = json!({"name": "old", "tags": ["a"], "meta": {"draft": true, "owner": "x"}});
+    let body = br#"{"name": "new", "tags": null, "meta": {"draft": false}}"#;
+
+    apply_patch(&mut document, Some(MERGE_PATCH), body).unwrap();
+
+    assert_eq!(document, json!({"name": "new", "meta": {"draft": false, "owner": "x"}}));
+}
+
+#[test]
+fn test_json_content_type_is_a_merge_patch() {
+    let mut document = json!({"a": 1});
+    apply_patch(&mut document, Some("application/json; charset=utf-8"), br#"{"b": 2}"#).unwrap();
+    assert_eq!(document, json!({"a": 1, "b": 2}));
+}
+
+#[test]
+fn test_json_patch_applies_operations() {
+    let mut document = json!({"items": [1, 2], "name": "cart"});
+    let body = br#"[
+        {"op": "add", "path": "/items/-", "value": 3},
+        {"op": "replace", "path": "/name", "value": "basket"},
+        {"op": "test", "path": "/items/0", "value": 1}
+    ]"#;
+
+    apply_patch(&mut document, Some(JSON_PATCH), body).unwrap();
+
+    assert_eq!(document, json!({"items": [1, 2, 3], "name": "basket"}));
+}
+
+#[test]
+fn test_failed_json_patch_is_unprocessable_and_atomic() {
+    let mut document = json!({"name": "cart"});
+    let body = br#"[
+        {"op": "replace", "path": "/name", "value": "basket"},
+        {"op": "remove", "path": "/missing"}
+    ]"#;
+
+    let error = apply_patch(&mut document, Some(JSON_PATCH), body).unwrap_err();
+
+    assert_eq!(error.status_code, 422);
+    assert!(error.message.contains("/missing"));
+    assert_eq!(document, json!({"name": "cart"}));
+}
+
+#[test]
+fn test_invalid_patch_documents_are_rejected() {
+    let mut document = json!({});
+    assert_eq!(apply_patch(&mut document, Some(JSON_PATCH), br#"{"op": "add"}"#).unwrap_err().status_code, 400);
+    assert_eq!(apply_patch(&mut document, None, b"not json").unwrap_err().status_code, 400);
+    assert_eq!(apply_patch(&mut document, Some("text/plain"), b"{}").unwrap_err().status_code, 415);
+}
+
+#[tokio::test]
+async fn test_patch_updates_stored_file() {
+    fs::write("responses/patch_profile.json", r#"{"name":"Ada","city":"London"}"#).unwrap();
+    let api = routes(patchable_endpoint("/api/profile", "patch_profile.json"), String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("PATCH")
+        .path("/api/profile")
+        .header("Content-Type", MERGE_PATCH)
+        .body(r#"{"city":null,"lang":"en"}"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(serde_json::from_slice::<Value>(res.body()).unwrap(), json!({"name": "Ada", "lang": "en"}));
+
+    let res = request()
+        .method("PATCH")
+        .path("/api/profile")
+        .header("Content-Type", JSON_PATCH)
+        .body(r#"[{"op":"replace","path":"/name","value":"Grace"}]"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    let res = request().method("GET").path("/api/profile").reply(&api).await;
+    assert_eq!(serde_json::from_slice::<Value>(res.body()).unwrap(), json!({"name": "Grace", "lang": "en"}));
+}
+
+#[tokio::test]
+async fn test_failed_patch_returns_422_and_keeps_file() {
+    fs::write("responses/patch_failing.json", r#"{"name":"Ada"}"#).unwrap();
+    let api = routes(patchable_endpoint("/api/failing", "patch_failing.json"), String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("PATCH")
+        .path("/api/failing")
+        .header("Content-Type", JSON_PATCH)
+        .body(r#"[{"op":"test","path":"/name","value":"Grace"}]"#)
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 422);
+    assert!(String::from_utf8_lossy(res.body()).starts_with("JSON Patch failed"));
+    assert_eq!(fs::read_to_string("responses/patch_failing.json").unwrap(), r#"{"name":"Ada"}"#);
+}
+
+#[tokio::test]
+async fn test_patch_resource_item_with_json_patch() {
+    fs::write("responses/patch_todos.json", r#"[{"id":1,"title":"write","tags":[]}]"#).unwrap();
+    let endpoints = HashMap::from([(
+        "/api/todos".to_string(),
+        serde_yaml::from_str::<Endpoint>("method: [GET, PATCH]\nresource:\n  file: patch_todos.json\n").unwrap(),
+    )]);
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("PATCH")
+        .path("/api/todos/1")
+        .header("Content-Type", JSON_PATCH)
+        .body(r#"[{"op":"add","path":"/tags/-","value":"urgent"},{"op":"replace","path":"/id","value":7}]"#)
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(serde_json::from_slice::<Value>(res.body()).unwrap(), json!({"id": 1, "title": "write", "tags": ["urgent"]}));
+}
+
+#[tokio::test]
+async fn test_cors_preflight_allows_patch() {
+    let api = routes(patchable_endpoint("/api/cors", "patch_cors.json"), String::from("responses"), new_rate_limit());
+
+    let res = request()
+        .method("OPTIONS")
+        .path("/api/cors")
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "PATCH")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert!(res.headers()["access-control-allow-methods"].to_str().unwrap().contains("PATCH"));
+}
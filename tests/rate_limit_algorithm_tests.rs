@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+use mockserver::rate_limit::{RateLimit, RateLimitAlgorithm, RateLimitState};
+
+fn limit(yaml: &str) -> RateLimit {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+/// Whether each request is allowed, the requests being made the given number of milliseconds after the first one.
+fn replay(limit: &RateLimit, times_ms: &[u64]) -> Vec<bool> {
+    let start = Instant::now();
+    let mut state = RateLimitState::default();
+    times_ms
+        .iter()
+        .map(|ms| limit.acquire(&mut state, start + Duration::from_millis(*ms)).allowed)
+        .collect()
+}
+
+#[test]
+fn test_fixed_window_allows_bursts_at_window_boundaries() {
+    let limit = limit("requests: 2\nwindow_ms: 1000\n");
+    assert_eq!(limit.algorithm, RateLimitAlgorithm::FixedWindow);
+
+    assert_eq!(
+        replay(&limit, &[0, 900, 950, 1001, 1002, 1003]),
+        [true, true, false, true, true, false]
+    );
+}
+
+#[test]
+fn test_sliding_log_counts_the_last_window() {
+    let limit = limit("requests: 2\nwindow_ms: 1000\nalgorithm: sliding_log\n");
+
+    assert_eq!(
+        replay(&limit, &[0, 900, 950, 1000, 1001, 1899, 1900]),
+        [true, true, false, true, false, false, true]
+    );
+}
+
+#[test]
+fn test_sliding_window_weighs_the_previous_window() {
+    let limit = limit("requests: 4\nwindow_ms: 1000\nalgorithm: sliding_window\n");
+
+    assert_eq!(
+        replay(&limit, &[0, 100, 200, 300, 400, 1500, 1500, 1500, 1750, 1750, 4500]),
+        // Half of the previous window's 4 requests still count at 1500, a quarter at 1750
+        [true, true, true, true, false, true, true, false, true, false, true]
+    );
+}
+
+#[test]
+fn test_token_bucket_bursts_then_refills() {
+    let limit = limit("requests: 1\nwindow_ms: 1000\nalgorithm: token_bucket\nburst: 3\n");
+
+    assert_eq!(
+        replay(&limit, &[0, 0, 0, 0, 500, 1000, 1000, 10000, 10000, 10000, 10000]),
+        [true, true, true, false, false, true, false, true, true, true, false]
+    );
+}
+
+#[test]
+fn test_token_bucket_burst_defaults_to_requests() {
+    let limit = limit("requests: 2\nwindow_ms: 1000\nalgorithm: token_bucket\n");
+
+    assert_eq!(replay(&limit, &[0, 0, 0, 500, 500]), [true, true, false, true, false]);
+}
+
+/// `(allowed, remaining, reset, retry_after)` of each request, durations in whole milliseconds.
+fn statuses(limit: &RateLimit, times_ms: &[u64]) -> Vec<(bool, usize, u128, u128)> {
+    let start = Instant::now();
+    let mut state = RateLimitState::default();
+    let ms = |duration: Duration| (duration.as_secs_f64() * 1000.0).round() as u128;
+    times_ms
+        .iter()
+        .map(|time| {
+            let status = limit.acquire(&mut state, start + Duration::from_millis(*time));
+            (status.allowed, status.remaining, ms(status.reset), ms(status.retry_after))
+        })
+        .collect()
+}
+
+#[test]
+fn test_fixed_window_status() {
+    let limit = limit("requests: 2\nwindow_ms: 1000\n");
+    assert_eq!(
+        statuses(&limit, &[0, 400, 500]),
+        [(true, 1, 1000, 0), (true, 0, 600, 600), (false, 0, 500, 500)]
+    );
+}
+
+#[test]
+fn test_sliding_log_status() {
+    let limit = limit("requests: 2\nwindow_ms: 1000\nalgorithm: sliding_log\n");
+    // The first slot frees up when the request made at 0 leaves the window
+    assert_eq!(
+        statuses(&limit, &[0, 300, 500]),
+        [(true, 1, 1000, 0), (true, 0, 700, 700), (false, 0, 500, 500)]
+    );
+}
+
+#[test]
+fn test_sliding_window_status() {
+    let limit = limit("requests: 4\nwindow_ms: 1000\nalgorithm: sliding_window\n");
+    let statuses = statuses(&limit, &[0, 100, 200, 300, 1100, 1100]);
+    // The 4 requests count fully until the next window starts, then fade
+    assert_eq!(statuses[3], (true, 0, 700, 700));
+    // 90% of the previous 4 requests plus 1 count at 1100; below 4 again once 75% of them have faded
+    assert_eq!(statuses[4], (true, 0, 900, 150));
+    assert_eq!(statuses[5], (false, 0, 900, 150));
+}
+
+#[test]
+fn test_token_bucket_status() {
+    let limit = limit("requests: 1\nwindow_ms: 1000\nalgorithm: token_bucket\nburst: 2\n");
+    assert_eq!(
+        statuses(&limit, &[0, 0, 250]),
+        [(true, 1, 1000, 0), (true, 0, 2000, 1000), (false, 0, 1750, 750)]
+    );
+}
+
+#[test]
+fn test_rate_limit_validation() {
+    assert!(limit("requests: 2\nwindow_ms: 1000\nalgorithm: token_bucket\nburst: 5\n").validate().is_ok());
+
+    let error = limit("requests: 2\nwindow_ms: 1000\nburst: 5\n").validate().unwrap_err();
+    assert!(error.to_string().contains("'burst'"));
+
+    let error = limit("requests: 2\nwindow_ms: 0\nalgorithm: sliding_window\n").validate().unwrap_err();
+    assert!(error.to_string().contains("'window_ms'"));
+
+    assert!(serde_yaml::from_str::<RateLimit>("requests: 2\nwindow_ms: 1000\nalgorithm: leaky\n").is_err());
+}
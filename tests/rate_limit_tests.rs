@@ -3,9 +3,9 @@ use std::fs;
 use std::time::Duration;
 use tokio::time::sleep;
 use warp::test::request;
-use mockserver::config::Endpoint;
+use mockserver::config::{Config, Endpoint};
 use mockserver::handler::routes;
-use mockserver::rate_limit::{new_rate_limit, RateLimit};
+use mockserver::rate_limit::{new_rate_limit, RateLimit, RateLimitKey};
 
 #[tokio::test]
 async fn test_request_under_limit() {
@@ -14,14 +14,16 @@ async fn test_request_under_limit() {
         "/test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 2,
                 window_ms: 1000,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
 
@@ -55,14 +57,16 @@ async fn test_exceeding_rate_limit() {
         "/rate_limited".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 2,
                 window_ms: 1000,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
 
@@ -103,28 +107,32 @@ async fn test_different_endpoints_have_separate_limits() {
         "/endpoint1".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 1,
                 window_ms: 1000,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
     endpoints.insert(
         "/endpoint2".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 1,
                 window_ms: 1000,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
 
@@ -157,14 +165,16 @@ async fn test_rate_limit_resets_after_window() {
         "/reset_test".to_string(),
         Endpoint {
             method: vec!["GET".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 1,
                 window_ms: 500,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
 
@@ -208,14 +218,16 @@ async fn test_different_methods_tracked_separately() {
         "/method_test".to_string(),
         Endpoint {
             method: vec!["GET".to_string(), "POST".to_string()],
-            file: "test_response.json".to_string(),
+            file: Some("test_response.json".to_string()),
             status_code: Some(200),
             authentication: None,
             delay: None,
             rate_limit: Some(RateLimit {
                 requests: 1,
                 window_ms: 1000,
+                ..Default::default()
             }),
+            ..Default::default()
         },
     );
 
@@ -247,4 +259,227 @@ async fn test_different_methods_tracked_separately() {
         .reply(&api)
         .await;
     assert_eq!(resp.status(), 200);
-}
\ No newline at end of file
+}
+fn keyed_endpoint(key_by: RateLimitKey) -> HashMap<String, Endpoint> {
+    HashMap::from([(
+        "/keyed".to_string(),
+        Endpoint {
+            method: vec!["GET".to_string()],
+            body: Some("ok".to_string()),
+            rate_limit: Some(RateLimit {
+                requests: 1,
+                window_ms: 60000,
+                key_by,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )])
+}
+
+#[tokio::test]
+async fn test_rate_limit_keyed_by_header() {
+    let api = routes(keyed_endpoint(RateLimitKey::Header("X-Api-Key".to_string())), "responses".to_string(), new_rate_limit());
+    let status = |key: Option<&'static str>| {
+        let api = api.clone();
+        async move {
+            let mut req = request().method("GET").path("/keyed");
+            if let Some(key) = key {
+                req = req.header("X-Api-Key", key);
+            }
+            req.reply(&api).await.status()
+        }
+    };
+
+    assert_eq!(status(Some("tenant-a")).await, 200);
+    assert_eq!(status(Some("tenant-a")).await, 429);
+    assert_eq!(status(Some("tenant-b")).await, 200);
+    // Requests without the header share one counter
+    assert_eq!(status(None).await, 200);
+    assert_eq!(status(None).await, 429);
+}
+
+#[tokio::test]
+async fn test_rate_limit_keyed_by_client_ip() {
+    let api = routes(keyed_endpoint(RateLimitKey::Ip), "responses".to_string(), new_rate_limit());
+    let status = |addr: &'static str, forwarded_for: Option<&'static str>| {
+        let api = api.clone();
+        async move {
+            let mut req = request().method("GET").path("/keyed").remote_addr(addr.parse().unwrap());
+            if let Some(forwarded_for) = forwarded_for {
+                req = req.header("X-Forwarded-For", forwarded_for);
+            }
+            req.reply(&api).await.status()
+        }
+    };
+
+    assert_eq!(status("10.0.0.1:5000", None).await, 200);
+    assert_eq!(status("10.0.0.1:5001", None).await, 429);
+    assert_eq!(status("10.0.0.2:5000", None).await, 200);
+    // Behind a proxy, the original client is the first forwarded address
+    assert_eq!(status("10.0.0.9:5000", Some("203.0.113.7, 10.0.0.9")).await, 200);
+    assert_eq!(status("10.0.0.8:5000", Some("203.0.113.7")).await, 429);
+}
+
+#[tokio::test]
+async fn test_rate_limit_keyed_by_jwt_claim() {
+    let api = routes(keyed_endpoint(RateLimitKey::Claim("tenant".to_string())), "responses".to_string(), new_rate_limit());
+    let token = |tenant: &str| {
+        let claims = serde_json::json!({"sub": "ada", "tenant": tenant});
+        jsonwebtoken::encode(&jsonwebtoken::Header::default(), &claims, &jsonwebtoken::EncodingKey::from_secret(b"secret")).unwrap()
+    };
+    let status = |token: String| {
+        let api = api.clone();
+        async move {
+            request()
+                .method("GET")
+                .path("/keyed")
+                .header("Authorization", format!("Bearer {}", token))
+                .reply(&api)
+                .await
+                .status()
+        }
+    };
+
+    assert_eq!(status(token("acme")).await, 200);
+    assert_eq!(status(token("acme")).await, 429);
+    assert_eq!(status(token("globex")).await, 200);
+}
+
+#[test]
+fn test_rate_limit_key_config() {
+    let parse = |yaml: &str| serde_yaml::from_str::<RateLimit>(yaml).unwrap().key_by;
+    assert_eq!(parse("requests: 1\nwindow_ms: 1000\n"), RateLimitKey::Global);
+    assert_eq!(parse("requests: 1\nwindow_ms: 1000\nkey_by: ip\n"), RateLimitKey::Ip);
+    assert_eq!(parse("requests: 1\nwindow_ms: 1000\nkey_by:\n  header: X-Api-Key\n"), RateLimitKey::Header("X-Api-Key".to_string()));
+    assert_eq!(parse("requests: 1\nwindow_ms: 1000\nkey_by: { claim: sub }\n"), RateLimitKey::Claim("sub".to_string()));
+}
+
+#[tokio::test]
+async fn test_token_bucket_endpoint() {
+    let endpoint: Endpoint = serde_yaml::from_str(
+        "method: [GET]\nbody: ok\nrate_limit:\n  requests: 1\n  window_ms: 60000\n  algorithm: token_bucket\n  burst: 2\n",
+    )
+    .unwrap();
+    let api = routes(HashMap::from([("/bucket".to_string(), endpoint)]), "responses".to_string(), new_rate_limit());
+
+    for expected in [200, 200, 429] {
+        let resp = request().method("GET").path("/bucket").reply(&api).await;
+        assert_eq!(resp.status(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_headers() {
+    let endpoint: Endpoint = serde_yaml::from_str(
+        "method: [GET]\nbody: ok\nrate_limit:\n  requests: 2\n  window_ms: 60000\n  ietf_headers: true\n",
+    )
+    .unwrap();
+    let api = routes(HashMap::from([("/limited".to_string(), endpoint)]), "responses".to_string(), new_rate_limit());
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+
+    let resp = request().method("GET").path("/limited").reply(&api).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["X-RateLimit-Limit"], "2");
+    assert_eq!(resp.headers()["X-RateLimit-Remaining"], "1");
+    let reset: u64 = resp.headers()["X-RateLimit-Reset"].to_str().unwrap().parse().unwrap();
+    assert!((now + 59..=now + 61).contains(&reset), "reset at {}, now {}", reset, now);
+    assert_eq!(resp.headers()["RateLimit-Limit"], "2");
+    assert_eq!(resp.headers()["RateLimit-Remaining"], "1");
+    assert_eq!(resp.headers()["RateLimit-Reset"], "60");
+    assert_eq!(resp.headers()["RateLimit-Policy"], "2;w=60");
+    assert!(resp.headers().get("Retry-After").is_none());
+
+    request().method("GET").path("/limited").reply(&api).await;
+    let resp = request().method("GET").path("/limited").reply(&api).await;
+    assert_eq!(resp.status(), 429);
+    assert_eq!(resp.headers()["X-RateLimit-Remaining"], "0");
+    assert_eq!(resp.headers()["Retry-After"], "60");
+}
+
+#[tokio::test]
+async fn test_no_rate_limit_headers_without_limit() {
+    let endpoint: Endpoint = serde_yaml::from_str("method: [GET]\nbody: ok\n").unwrap();
+    let api = routes(HashMap::from([("/free".to_string(), endpoint)]), "responses".to_string(), new_rate_limit());
+
+    let resp = request().method("GET").path("/free").reply(&api).await;
+    assert!(resp.headers().get("X-RateLimit-Limit").is_none());
+    assert!(resp.headers().get("RateLimit-Limit").is_none());
+}
+
+const SHARED_LIMITS: &str = r#"
+rate_limit:
+  requests: 8
+  window_ms: 60000
+rate_limit_groups:
+  orders:
+    requests: 5
+    window_ms: 60000
+/api/orders:
+  method: [GET, POST]
+  body: '[]'
+  rate_limit_group: orders
+  rate_limit:
+    requests: 1
+    window_ms: 60000
+/api/orders/archive:
+  method: [GET]
+  body: '[]'
+  rate_limit_group: orders
+/api/users:
+  method: [GET]
+  body: '[]'
+"#;
+
+#[tokio::test]
+async fn test_group_and_server_wide_limits() {
+    let config: Config = serde_yaml::from_str(SHARED_LIMITS).unwrap();
+    let api = routes(config, "responses".to_string(), new_rate_limit());
+    let status = |method: &'static str, path: &'static str| {
+        let api = api.clone();
+        async move { request().method(method).path(path).reply(&api).await.status() }
+    };
+
+    // The endpoint's own limit turns down the second GET, which the group does not count
+    assert_eq!(status("GET", "/api/orders").await, 200);
+    assert_eq!(status("GET", "/api/orders").await, 429);
+    assert_eq!(status("POST", "/api/orders").await, 201);
+
+    // The group allows 5 requests over its endpoints: 2 so far
+    for _ in 0..3 {
+        assert_eq!(status("GET", "/api/orders/archive").await, 200);
+    }
+    assert_eq!(status("GET", "/api/orders/archive").await, 429);
+
+    // The server allows 8 requests over every endpoint: 5 so far
+    for _ in 0..3 {
+        assert_eq!(status("GET", "/api/users").await, 200);
+    }
+    assert_eq!(status("GET", "/api/users").await, 429);
+}
+
+#[tokio::test]
+async fn test_most_restrictive_limit_is_reported() {
+    let config: Config = serde_yaml::from_str(SHARED_LIMITS).unwrap();
+    let api = routes(config, "responses".to_string(), new_rate_limit());
+
+    let resp = request().method("GET").path("/api/orders/archive").reply(&api).await;
+    assert_eq!(resp.headers()["X-RateLimit-Limit"], "5");
+    assert_eq!(resp.headers()["X-RateLimit-Remaining"], "4");
+
+    let resp = request().method("GET").path("/api/users").reply(&api).await;
+    assert_eq!(resp.headers()["X-RateLimit-Limit"], "8");
+    assert_eq!(resp.headers()["X-RateLimit-Remaining"], "6");
+
+    let resp = request().method("GET").path("/api/orders").reply(&api).await;
+    assert_eq!(resp.headers()["X-RateLimit-Limit"], "1");
+    assert_eq!(resp.headers()["X-RateLimit-Remaining"], "0");
+}
+
+#[test]
+fn test_undefined_rate_limit_group() {
+    let path = std::env::temp_dir().join("mockserver_undefined_group.yaml");
+    fs::write(&path, "/api/orders:\n  method: [GET]\n  body: '[]'\n  rate_limit_group: order\n").unwrap();
+    let error = mockserver::config::load_config(path.to_str().unwrap()).unwrap_err();
+    assert!(error.to_string().contains("undefined rate limit group 'order'"));
+}
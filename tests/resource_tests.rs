@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs;
+use serde_json::{json, Value};
+use warp::test::request;
+use mockserver::config::{load_config, Endpoint};
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn resource_endpoints(key: &str, resource: &str) -> HashMap<String, Endpoint> {
+    let yaml = format!(
+        r#"
+        method: [GET, POST, PUT, PATCH, DELETE]
+        resource:
+          {}
+        "#,
+        resource
+    );
+    HashMap::from([(key.to_string(), serde_yaml::from_str::<Endpoint>(&yaml).unwrap())])
+}
+
+fn body_json(body: &[u8]) -> Value {
+    serde_json::from_slice(body).unwrap()
+}
+
+#[tokio::test]
+async fn test_resource_crud_flow() {
+    let _ = fs::remove_file("responses/resource_items.json");
+    let api = routes(resource_endpoints("/api/items", "file: resource_items.json"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/api/items").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(body_json(res.body()), json!([]));
+
+    let res = request().method("POST").path("/api/items").body(r#"{"name":"first"}"#).reply(&api).await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers()["Location"], "/api/items/1");
+    assert_eq!(body_json(res.body()), json!({"id": 1, "name": "first"}));
+
+    let res = request().method("POST").path("/api/items").body(r#"{"name":"second"}"#).reply(&api).await;
+    assert_eq!(body_json(res.body())["id"], json!(2));
+
+    let res = request().method("GET").path("/api/items/2").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(body_json(res.body()), json!({"id": 2, "name": "second"}));
+
+    let res = request().method("PUT").path("/api/items/1").body(r#"{"id":9,"title":"replaced"}"#).reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(body_json(res.body()), json!({"id": 1, "title": "replaced"}));
+
+    let res = request().method("PATCH").path("/api/items/2").body(r#"{"done":true}"#).reply(&api).await;
+    assert_eq!(body_json(res.body()), json!({"id": 2, "name": "second", "done": true}));
+
+    let res = request().method("DELETE").path("/api/items/1").reply(&api).await;
+    assert_eq!(res.status(), 204);
+
+    let res = request().method("GET").path("/api/items/1").reply(&api).await;
+    assert_eq!(res.status(), 404);
+
+    let stored: Value = body_json(&fs::read("responses/resource_items.json").unwrap());
+    assert_eq!(stored, json!([{"id": 2, "name": "second", "done": true}]));
+}
+
+#[tokio::test]
+async fn test_resource_filters_and_generates_uuid_ids() {
+    fs::write(
+        "responses/resource_users.json",
+        r#"[{"id":"alice","role":"admin"},{"id":"bob","role":"user"}]"#,
+    )
+    .unwrap();
+    let api = routes(resource_endpoints("/api/users", "file: resource_users.json"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/api/users?role=admin").reply(&api).await;
+    assert_eq!(body_json(res.body()), json!([{"id": "alice", "role": "admin"}]));
+
+    let res = request().method("POST").path("/api/users").body(r#"{"role":"user"}"#).reply(&api).await;
+    assert_eq!(res.status(), 201);
+    let id = body_json(res.body())["id"].as_str().unwrap().to_string();
+    assert_eq!(id.len(), 36);
+
+    let res = request().method("GET").path(&format!("/api/users/{}", id)).reply(&api).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_resource_ignores_query_parameters_that_are_not_fields() {
+    fs::write(
+        "responses/resource_products.json",
+        r#"[{"id":1,"category":"books"},{"id":2,"category":"games"}]"#,
+    )
+    .unwrap();
+    let api = routes(resource_endpoints("/api/products", "file: resource_products.json"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/api/products?_=1700000000").reply(&api).await;
+    assert_eq!(body_json(res.body()), json!([{"id": 1, "category": "books"}, {"id": 2, "category": "games"}]));
+
+    let res = request().method("GET").path("/api/products?category=games&_=1700000000").reply(&api).await;
+    assert_eq!(body_json(res.body()), json!([{"id": 2, "category": "games"}]));
+}
+
+#[tokio::test]
+async fn test_resource_rejects_invalid_requests() {
+    fs::write("responses/resource_invalid.json", r#"[{"id":1}]"#).unwrap();
+    let api = routes(resource_endpoints("/api/invalid", "file: resource_invalid.json"), String::from("responses"), new_rate_limit());
+
+    let res = request().method("POST").path("/api/invalid").body(r#"{"id":1}"#).reply(&api).await;
+    assert_eq!(res.status(), 409);
+
+    let res = request().method("POST").path("/api/invalid").body("[1, 2]").reply(&api).await;
+    assert_eq!(res.status(), 400);
+
+    let res = request().method("POST").path("/api/invalid/1").body("{}").reply(&api).await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(res.headers()["Allow"], "GET, PUT, PATCH, DELETE, HEAD, OPTIONS");
+
+    let res = request().method("DELETE").path("/api/invalid/2").reply(&api).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_resource_backed_by_directory() {
+    let _ = fs::remove_dir_all("responses/resource_notes");
+    let api = routes(
+        resource_endpoints("/api/notes", "directory: resource_notes\n          id_field: slug"),
+        String::from("responses"),
+        new_rate_limit(),
+    );
+
+    let res = request().method("POST").path("/api/notes").body(r#"{"slug":"hello","text":"hi"}"#).reply(&api).await;
+    assert_eq!(res.status(), 201);
+    assert!(fs::metadata("responses/resource_notes/hello.json").is_ok());
+
+    let res = request().method("GET").path("/api/notes/hello").reply(&api).await;
+    assert_eq!(body_json(res.body()), json!({"slug": "hello", "text": "hi"}));
+
+    let res = request().method("DELETE").path("/api/notes/hello").reply(&api).await;
+    assert_eq!(res.status(), 204);
+    assert!(fs::metadata("responses/resource_notes/hello.json").is_err());
+}
+
+#[test]
+fn test_load_config_rejects_invalid_resources() {
+    let path = std::env::temp_dir().join("mockserver_resource_both.yaml");
+    fs::write(&path, "/api/items:\n  method: [GET]\n  resource:\n    file: items.json\n    directory: items\n").unwrap();
+    let error = load_config(path.to_str().unwrap()).unwrap_err();
+    assert!(format!("{:#}", error).contains("Exactly one of 'file' or 'directory'"));
+
+    let path = std::env::temp_dir().join("mockserver_resource_clash.yaml");
+    fs::write(
+        &path,
+        "/api/items:\n  method: [GET]\n  resource:\n    file: items.json\n/api/items/{id}:\n  method: [GET]\n  file: item.json\n",
+    )
+    .unwrap();
+    let error = load_config(path.to_str().unwrap()).unwrap_err();
+    assert!(error.to_string().contains("already served by the resource"));
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+use mockserver::response::content_type;
+
+#[test]
+fn test_content_type_inferred_from_extension() {
+    assert_eq!(content_type("responses/user.json"), "application/json");
+    assert_eq!(content_type("responses/user"), "application/json");
+    assert_eq!(content_type("responses/feed.XML"), "application/xml");
+    assert_eq!(content_type("responses/index.html"), "text/html; charset=utf-8");
+    assert_eq!(content_type("responses/notes.txt"), "text/plain; charset=utf-8");
+    assert_eq!(content_type("responses/report.csv"), "text/csv; charset=utf-8");
+    assert_eq!(content_type("responses/avatar.png"), "image/png");
+    assert_eq!(content_type("responses/archive.unknown"), "application/octet-stream");
+}
+
+#[tokio::test]
+async fn test_served_file_has_inferred_content_type() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/feed".to_string(),
+        Endpoint {
+            method: vec!["GET".to_string()],
+            file: Some("response_feed.xml".to_string()),
+            ..Default::default()
+        },
+    );
+
+    fs::write("responses/response_feed.xml", "<feed/>").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/feed").reply(&api).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Content-Type"], "application/xml");
+}
+
+#[tokio::test]
+async fn test_configured_headers_are_added_to_responses() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/session/{id}".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+              - POST
+            file: response_session.json
+            headers:
+              Cache-Control: no-cache
+              Content-Type: application/vnd.api+json
+              Location: /session/{id}
+              Set-Cookie:
+                - session=abc; HttpOnly
+                - theme=dark
+            POST:
+              file: response_session.json
+              headers:
+                Cache-Control: no-store
+            "#,
+        )
+        .unwrap(),
+    );
+
+    fs::write("responses/response_session.json", "{}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/session/7").reply(&api).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["Cache-Control"], "no-cache");
+    assert_eq!(res.headers()["Content-Type"], "application/vnd.api+json");
+    assert_eq!(res.headers()["Location"], "/session/7");
+    let cookies: Vec<_> = res.headers().get_all("Set-Cookie").iter().collect();
+    assert_eq!(cookies, vec!["session=abc; HttpOnly", "theme=dark"]);
+
+    // Method blocks override endpoint headers with the same name
+    let res = request().method("POST").path("/session/7").reply(&api).await;
+    assert_eq!(res.headers()["Cache-Control"], "no-store");
+    assert_eq!(res.headers()["Location"], "/session/7");
+}
+
+const PNG_BYTES: [u8; 12] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0xFF, 0xFE, 0x80];
+
+#[tokio::test]
+async fn test_get_binary_file() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/avatar".to_string(),
+        Endpoint {
+            method: vec!["GET".to_string()],
+            file: Some("response_avatar.png".to_string()),
+            ..Default::default()
+        },
+    );
+
+    fs::write("responses/response_avatar.png", PNG_BYTES).unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/avatar").reply(&api).await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body().as_ref(), PNG_BYTES);
+    assert_eq!(res.headers()["Content-Type"], "image/png");
+    assert_eq!(res.headers()["Content-Length"], PNG_BYTES.len().to_string().as_str());
+}
+
+#[tokio::test]
+async fn test_post_binary_body_is_stored_unchanged() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/upload".to_string(),
+        Endpoint {
+            method: vec!["PUT".to_string(), "GET".to_string()],
+            file: Some("response_upload.png".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("PUT").path("/upload").body(PNG_BYTES).reply(&api).await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(fs::read("responses/response_upload.png").unwrap(), PNG_BYTES);
+
+    let res = request().method("GET").path("/upload").reply(&api).await;
+    assert_eq!(res.body().as_ref(), PNG_BYTES);
+}
+
+#[tokio::test]
+async fn test_inline_bodies() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/inline".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+            body: '{"ok":true}'
+            POST:
+              json:
+                method: "{{method}}"
+                created: true
+              status_code: 201
+            PUT:
+              body: "updated {{body}}"
+            template: true
+            "#,
+        )
+        .unwrap(),
+    );
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/inline").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"ok\":true}");
+    assert_eq!(res.headers()["Content-Type"], "application/json");
+
+    let res = request().method("POST").path("/inline").reply(&api).await;
+    assert_eq!(res.status(), 201);
+    let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body, serde_json::json!({"method": "POST", "created": true}));
+
+    let res = request().method("PUT").path("/inline").body("Laptop").reply(&api).await;
+    assert_eq!(res.body(), "updated Laptop");
+    assert_eq!(res.headers()["Content-Type"], "text/plain; charset=utf-8");
+}
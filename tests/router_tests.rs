@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fs;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+use mockserver::router::Router;
+
+fn endpoint(file: &str) -> Endpoint {
+    Endpoint {
+        method: vec!["GET".to_string()],
+        file: Some(file.to_string()),
+        status_code: None,
+        authentication: None,
+        delay: None,
+        rate_limit: None,
+        ..Default::default()
+    }
+}
+
+fn router(patterns: &[&str]) -> Router {
+    let endpoints: HashMap<String, Endpoint> = patterns
+        .iter()
+        .map(|pattern| (pattern.to_string(), endpoint(pattern)))
+        .collect();
+    Router::new(&endpoints).unwrap()
+}
+
+#[test]
+fn test_captures_path_parameters() {
+    let router = router(&["/api/user/{id}", "/api/user/{id}/orders/{order}"]);
+
+    let found = router.find("/api/user/42/orders/7").unwrap();
+    assert_eq!(found.pattern, "/api/user/{id}/orders/{order}");
+    assert_eq!(found.params["id"], "42");
+    assert_eq!(found.params["order"], "7");
+
+    let found = router.find("/api/user/42").unwrap();
+    assert_eq!(found.pattern, "/api/user/{id}");
+    assert!(router.find("/api/user").is_none());
+    assert!(router.find("/api/user/").is_none());
+}
+
+#[test]
+fn test_wildcard_captures_remaining_segments() {
+    let router = router(&["/api/files/*rest"]);
+
+    let found = router.find("/api/files/docs/2024/report.pdf").unwrap();
+    assert_eq!(found.params["rest"], "docs/2024/report.pdf");
+    assert!(router.find("/api/files").is_none());
+}
+
+#[test]
+fn test_literal_beats_parameter_beats_wildcard() {
+    let router = router(&["/api/*rest", "/api/user/{id}", "/api/user/me", "/api/{resource}/me"]);
+
+    assert_eq!(router.find("/api/user/me").unwrap().pattern, "/api/user/me");
+    assert_eq!(router.find("/api/user/1").unwrap().pattern, "/api/user/{id}");
+    assert_eq!(router.find("/api/order/me").unwrap().pattern, "/api/{resource}/me");
+    assert_eq!(router.find("/api/order/1").unwrap().pattern, "/api/*rest");
+}
+
+#[test]
+fn test_ambiguous_routes_resolve_deterministically() {
+    let router = router(&["/api/{b}", "/api/{a}"]);
+
+    let found = router.find("/api/x").unwrap();
+    assert_eq!(found.pattern, "/api/{a}");
+    assert_eq!(found.params["a"], "x");
+}
+
+#[test]
+fn test_parent_directory_segments_are_not_captured() {
+    let router = router(&["/files/{name}", "/static/*rest"]);
+
+    assert!(router.find("/files/..").is_none());
+    assert!(router.find("/files/%2E%2E").is_none());
+    assert!(router.find("/static/a/../../secret").is_none());
+    assert_eq!(router.find("/files/my%20file").unwrap().params["name"], "my file");
+}
+
+#[test]
+fn test_wildcard_must_be_last_segment() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/api/*rest/more".to_string(), endpoint("x.json"));
+
+    assert!(Router::new(&endpoints).is_err());
+}
+
+#[tokio::test]
+async fn test_path_parameter_selects_response_file() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/router/user/{id}".to_string(), endpoint("router_user_{id}.json"));
+
+    fs::write("responses/router_user_1.json", "{\"id\": 1}").unwrap();
+    fs::write("responses/router_user_2.json", "{\"id\": 2}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/router/user/1").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"id\": 1}");
+
+    let res = request().method("GET").path("/router/user/2").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body(), "{\"id\": 2}");
+
+    let res = request().method("GET").path("/router/user/3").reply(&api).await;
+    assert_eq!(res.status(), 404);
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn cart_endpoints() -> HashMap<String, Endpoint> {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/api/cart".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+            json:
+              items: [1, 2]
+            variants:
+              - scenario: cart
+                required_state: checked_out
+                response:
+                  json:
+                    items: []
+            "#,
+        )
+        .unwrap(),
+    );
+    endpoints.insert(
+        "/api/cart/checkout".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - POST
+            variants:
+              - scenario: cart
+                required_state: Started
+                new_state: checked_out
+                response:
+                  status_code: 200
+                  body: '{"ok":true}'
+            body: '{"error":"cart is empty"}'
+            status_code: 409
+            "#,
+        )
+        .unwrap(),
+    );
+    endpoints
+}
+
+#[tokio::test]
+async fn test_scenario_transition_changes_other_endpoint() {
+    let api = routes(cart_endpoints(), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/api/cart").reply(&api).await;
+    assert_eq!(res.body(), "{\"items\":[1,2]}");
+
+    let res = request().method("POST").path("/api/cart/checkout").reply(&api).await;
+    assert_eq!(res.status(), 200);
+
+    let res = request().method("GET").path("/api/cart").reply(&api).await;
+    assert_eq!(res.body(), "{\"items\":[]}");
+
+    // The checkout variant is only active in the initial state
+    let res = request().method("POST").path("/api/cart/checkout").reply(&api).await;
+    assert_eq!(res.status(), 409);
+}
+
+#[tokio::test]
+async fn test_head_does_not_move_scenarios() {
+    let endpoint = serde_yaml::from_str::<Endpoint>(
+        r#"
+        method: [GET]
+        body: welcome back
+        variants:
+          - scenario: visits
+            required_state: Started
+            new_state: visited
+            response:
+              body: welcome
+              status_code: 201
+        "#,
+    )
+    .unwrap();
+    let api = routes(HashMap::from([("/api/welcome".to_string(), endpoint)]), String::from("responses"), new_rate_limit());
+
+    let res = request().method("HEAD").path("/api/welcome").reply(&api).await;
+    assert_eq!(res.status(), 201);
+
+    let res = request().method("GET").path("/api/welcome").reply(&api).await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.body(), "welcome");
+
+    let res = request().method("GET").path("/api/welcome").reply(&api).await;
+    assert_eq!(res.body(), "welcome back");
+}
+
+#[tokio::test]
+async fn test_scenario_states_are_readable_and_resettable() {
+    let api = routes(cart_endpoints(), String::from("responses"), new_rate_limit());
+
+    let res = request().method("GET").path("/__admin/scenarios").reply(&api).await;
+    let states: HashMap<String, String> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(states["cart"], "Started");
+
+    let res = request()
+        .method("PUT")
+        .path("/__admin/scenarios/cart/state")
+        .json(&serde_json::json!({"state": "checked_out"}))
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 204);
+
+    let res = request().method("GET").path("/api/cart").reply(&api).await;
+    assert_eq!(res.body(), "{\"items\":[]}");
+
+    let res = request().method("POST").path("/__admin/scenarios/reset").reply(&api).await;
+    assert_eq!(res.status(), 204);
+
+    let res = request().method("GET").path("/__admin/scenarios").reply(&api).await;
+    let states: HashMap<String, String> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(states["cart"], "Started");
+
+    let res = request().method("GET").path("/api/cart").reply(&api).await;
+    assert_eq!(res.body(), "{\"items\":[1,2]}");
+}
+
+#[tokio::test]
+async fn test_invalid_scenario_state_update_is_rejected() {
+    let api = routes(cart_endpoints(), String::from("responses"), new_rate_limit());
+
+    for body in ["checked_out", "{\"name\": \"checked_out\"}"] {
+        let res = request()
+            .method("PUT")
+            .path("/__admin/scenarios/cart/state")
+            .body(body)
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), 400);
+    }
+
+    let res = request().method("GET").path("/__admin/scenarios").reply(&api).await;
+    let states: HashMap<String, String> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(states["cart"], "Started");
+}
+
+#[test]
+fn test_scenario_state_without_scenario_is_invalid() {
+    let endpoint: Endpoint = serde_yaml::from_str(
+        r#"
+        method: [GET]
+        body: ok
+        variants:
+          - required_state: checked_out
+            response:
+              body: empty
+        "#,
+    )
+    .unwrap();
+
+    assert!(endpoint.validate().is_err());
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn endpoint(mode: &str) -> Endpoint {
+    serde_yaml::from_str(&format!(
+        r#"
+        method:
+          - GET
+        sequence: {}
+        responses:
+          - body: first
+            status_code: 500
+          - body: second
+            status_code: 503
+          - body: third
+        "#,
+        mode
+    ))
+    .unwrap()
+}
+
+async fn statuses(api: &(impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone + 'static), path: &str, calls: usize) -> Vec<u16> {
+    let mut statuses = Vec::new();
+    for _ in 0..calls {
+        statuses.push(request().method("GET").path(path).reply(api).await.status().as_u16());
+    }
+    statuses
+}
+
+#[tokio::test]
+async fn test_sequence_sticks_on_last_response() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/flaky".to_string(), endpoint("stick"));
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    assert_eq!(statuses(&api, "/flaky", 5).await, vec![500, 503, 200, 200, 200]);
+}
+
+#[tokio::test]
+async fn test_sequence_cycles() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/flaky".to_string(), endpoint("cycle"));
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    assert_eq!(statuses(&api, "/flaky", 5).await, vec![500, 503, 200, 500, 503]);
+}
+
+#[tokio::test]
+async fn test_head_does_not_consume_responses() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/flaky".to_string(), endpoint("stick"));
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    for _ in 0..3 {
+        let res = request().method("HEAD").path("/flaky").reply(&api).await;
+        assert_eq!(res.status(), 500);
+        assert!(res.body().is_empty());
+    }
+    assert_eq!(statuses(&api, "/flaky", 3).await, vec![500, 503, 200]);
+}
+
+#[tokio::test]
+async fn test_random_sequence_respects_weights() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert(
+        "/random".to_string(),
+        serde_yaml::from_str::<Endpoint>(
+            r#"
+            method:
+              - GET
+            sequence: random
+            responses:
+              - body: never
+                status_code: 500
+                weight: 0
+              - body: always
+                weight: 3
+            "#,
+        )
+        .unwrap(),
+    );
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+
+    assert_eq!(statuses(&api, "/random", 10).await, vec![200; 10]);
+}
+
+#[tokio::test]
+async fn test_sequence_can_be_reset_through_admin_endpoint() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/flaky".to_string(), endpoint("stick"));
+    endpoints.insert("/other".to_string(), endpoint("stick"));
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    assert_eq!(statuses(&api, "/flaky", 3).await, vec![500, 503, 200]);
+    assert_eq!(statuses(&api, "/other", 1).await, vec![500]);
+
+    let res = request().method("GET").path("/__admin/sequences").reply(&api).await;
+    let calls: HashMap<String, usize> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(calls["/flaky"], 3);
+    assert_eq!(calls["/other"], 1);
+
+    let res = request().method("POST").path("/__admin/sequences/reset?endpoint=/flaky").reply(&api).await;
+    assert_eq!(res.status(), 204);
+    assert_eq!(statuses(&api, "/flaky", 1).await, vec![500]);
+    assert_eq!(statuses(&api, "/other", 1).await, vec![503]);
+
+    let res = request().method("POST").path("/__admin/sequences/reset").reply(&api).await;
+    assert_eq!(res.status(), 204);
+    assert_eq!(statuses(&api, "/flaky", 1).await, vec![500]);
+    assert_eq!(statuses(&api, "/other", 1).await, vec![500]);
+}
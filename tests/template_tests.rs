@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fs;
+use serde_json::Value;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+fn endpoint(file: &str, template: bool) -> Endpoint {
+    Endpoint {
+        method: vec!["GET".to_string(), "POST".to_string()],
+        file: Some(file.to_string()),
+        template,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_template_renders_request_values() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/template/user/{id}".to_string(), endpoint("template_user.json", true));
+
+    fs::write(
+        "responses/template_user.json",
+        r#"{"id": "{{params.id}}", "status": "{{query.status}}", "tenant": "{{headers.[x-tenant]}}", "method": "{{method}}", "path": "{{path}}"}"#,
+    )
+    .unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("GET")
+        .path("/template/user/42?status=active")
+        .header("X-Tenant", "acme")
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.body(),
+        r#"{"id": "42", "status": "active", "tenant": "acme", "method": "GET", "path": "/template/user/42"}"#
+    );
+}
+
+#[tokio::test]
+async fn test_template_renders_json_body() {
+    let mut endpoints = HashMap::new();
+    let mut echo = endpoint("template_echo.json", true);
+    echo.variants = serde_yaml::from_str("- body: { contains_json: { name: Ada } }\n  response: { file: template_echo.json }").unwrap();
+    endpoints.insert("/template/echo".to_string(), echo);
+
+    fs::write(
+        "responses/template_echo.json",
+        r#"{"greeting": "Hello {{body.name}}", "tags": {{json body.tags}}}"#,
+    )
+    .unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request()
+        .method("POST")
+        .path("/template/echo")
+        .body(r#"{"name": "Ada", "tags": ["a", "b"]}"#)
+        .reply(&api)
+        .await;
+
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.body(), r#"{"greeting": "Hello Ada", "tags": ["a","b"]}"#);
+}
+
+#[tokio::test]
+async fn test_template_helpers() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/template/helpers".to_string(), endpoint("template_helpers.json", true));
+
+    fs::write(
+        "responses/template_helpers.json",
+        r#"{"id": "{{uuid}}", "created": {{now "epoch"}}, "year": "{{now "%Y"}}", "seq": {{counter "template_helpers"}}}"#,
+    )
+    .unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let first: Value = serde_json::from_slice(request().path("/template/helpers").reply(&api).await.body()).unwrap();
+    let second: Value = serde_json::from_slice(request().path("/template/helpers").reply(&api).await.body()).unwrap();
+
+    assert_eq!(first["id"].as_str().unwrap().len(), 36);
+    assert_ne!(first["id"], second["id"]);
+    assert!(first["created"].as_i64().unwrap() > 1_600_000_000_000);
+    assert_eq!(first["year"].as_str().unwrap().len(), 4);
+    assert_eq!(first["seq"], 1);
+    assert_eq!(second["seq"], 2);
+}
+
+#[tokio::test]
+async fn test_head_does_not_render_templates() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/template/head".to_string(), endpoint("template_head.json", true));
+
+    fs::write("responses/template_head.json", r#"{"seq": {{counter "template_head"}}}"#).unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("HEAD").path("/template/head").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert!(res.body().is_empty());
+
+    let res = request().method("GET").path("/template/head").reply(&api).await;
+    assert_eq!(res.body(), r#"{"seq": 1}"#);
+}
+
+#[tokio::test]
+async fn test_files_are_not_rendered_without_template_flag() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/template/raw".to_string(), endpoint("template_raw.json", false));
+
+    fs::write("responses/template_raw.json", "{\"id\": \"{{uuid}}\"}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/template/raw").reply(&api).await;
+
+    assert_eq!(res.body(), "{\"id\": \"{{uuid}}\"}");
+}
+
+#[tokio::test]
+async fn test_invalid_template_returns_server_error() {
+    let mut endpoints = HashMap::new();
+    endpoints.insert("/template/invalid".to_string(), endpoint("template_invalid.json", true));
+
+    fs::write("responses/template_invalid.json", "{{#if}}").unwrap();
+
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let res = request().method("GET").path("/template/invalid").reply(&api).await;
+
+    assert_eq!(res.status(), 500);
+}
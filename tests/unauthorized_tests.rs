@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use warp::http::Response;
+use bytes::Bytes;
+use warp::test::request;
+use mockserver::config::Endpoint;
+use mockserver::handler::routes;
+use mockserver::rate_limit::new_rate_limit;
+
+async fn get(endpoint: &str, header: Option<(&str, &str)>) -> Response<Bytes> {
+    let endpoints = HashMap::from([("/protected".to_string(), serde_yaml::from_str::<Endpoint>(endpoint).unwrap())]);
+    let api = routes(endpoints, String::from("responses"), new_rate_limit());
+    let mut req = request().method("GET").path("/protected");
+    if let Some((name, value)) = header {
+        req = req.header(name, value);
+    }
+    req.reply(&api).await
+}
+
+fn challenges(response: &Response<Bytes>) -> Vec<&str> {
+    response
+        .headers()
+        .get_all("WWW-Authenticate")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_basic_challenge() {
+    let res = get("method: [GET]\nbody: ok\nauthentication:\n  basic: { user: admin, password: secret }\n", None).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.body(), "Unauthorized\n");
+    assert_eq!(challenges(&res), ["Basic realm=\"mockserver\""]);
+
+    let res = get(
+        "method: [GET]\nbody: ok\nauthentication:\n  basic: { user: admin, password: secret, realm: Back office }\n",
+        Some(("Authorization", "Basic YWRtaW46d3Jvbmc=")),
+    )
+    .await;
+    assert_eq!(challenges(&res), ["Basic realm=\"Back office\""]);
+}
+
+#[tokio::test]
+async fn test_bearer_challenge_reports_invalid_tokens() {
+    let endpoint = "method: [GET]\nbody: ok\nauthentication:\n  bearer: { token: valid_token }\n";
+
+    let res = get(endpoint, None).await;
+    assert_eq!(challenges(&res), ["Bearer realm=\"mockserver\""]);
+
+    let res = get(endpoint, Some(("Authorization", "Bearer expired_token"))).await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(challenges(&res), ["Bearer realm=\"mockserver\", error=\"invalid_token\""]);
+}
+
+#[tokio::test]
+async fn test_one_challenge_per_alternative() {
+    let res = get(
+        "method: [GET]\nbody: ok\nauthentication:\n  - basic: { user: admin, password: secret }\n  - bearer: { token: valid_token }\n  - api_key: { keys: [{ key: sk_test_a }] }\n",
+        None,
+    )
+    .await;
+    assert_eq!(challenges(&res), ["Basic realm=\"mockserver\"", "Bearer realm=\"mockserver\""]);
+
+    let res = get("method: [GET]\nbody: ok\nauthentication:\n  api_key: { keys: [{ key: sk_test_a }] }\n", None).await;
+    assert_eq!(res.status(), 401);
+    assert!(challenges(&res).is_empty());
+}
+
+#[tokio::test]
+async fn test_custom_unauthorized_response() {
+    let res = get(
+        r#"
+method: [GET]
+body: ok
+authentication:
+  bearer: { token: valid_token }
+unauthorized:
+  status: 403
+  json: { error: forbidden }
+  headers:
+    X-Reason: denied
+"#,
+        Some(("Authorization", "Bearer other_token")),
+    )
+    .await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), r#"{"error":"forbidden"}"#);
+    assert_eq!(res.headers()["X-Reason"], "denied");
+    assert!(challenges(&res).is_empty());
+
+    let res = get(
+        r#"
+method: [GET]
+body: ok
+authentication:
+  basic: { user: admin, password: secret }
+unauthorized:
+  body: Log in first
+  headers:
+    WWW-Authenticate: Custom realm="sso"
+"#,
+        None,
+    )
+    .await;
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.body(), "Log in first");
+    assert_eq!(challenges(&res), ["Custom realm=\"sso\""]);
+
+    let res = get(
+        "method: [GET]\nbody: ok\nauthentication:\n  basic: { user: admin, password: secret }\nunauthorized: { status: 403 }\n",
+        None,
+    )
+    .await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), "Forbidden\n");
+}